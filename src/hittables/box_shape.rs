@@ -71,7 +71,7 @@ impl BoxShape {
 }
 
 impl Hitable for BoxShape {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         self.hit_objects.hit(r, t_min, t_max)
     }
 
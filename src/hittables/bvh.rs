@@ -1,101 +1,321 @@
 use crate::hittables::{HitRecord, Hitable};
+use crate::math::Vec3;
 use crate::physics::{surrounding_box, Ray, AABB};
-use std::cmp::Ordering;
+use rayon::prelude::*;
 use std::f32;
+use std::fmt;
+
+const SAH_BINS: usize = 12;
+// Below this many primitives a subtree is cheaper to build than to hand to another thread.
+const PARALLEL_BUILD_THRESHOLD: usize = 1024;
+pub(crate) const TRAVERSAL_COST: f32 = 1.0;
+pub(crate) const INTERSECTION_COST: f32 = 1.0;
+
+/// How `BVH::with_split` divides the objects of a node between its children.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SplitMethod {
+    /// Halves the objects at the median along the widest axis.
+    Median,
+    /// Picks the cheapest of the binned surface area heuristic candidates.
+    Sah,
+}
+
+/// Tree quality figures, `sah_cost` is the expected cost of a ray that hits the root box.
+#[derive(Copy, Clone, Debug)]
+pub struct BVHStats {
+    pub sah_cost: f32,
+    pub depth: usize,
+    pub leaf_count: usize,
+    pub node_count: usize,
+}
+
+impl fmt::Display for BVHStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "SAH cost {:.2}, depth {}, {} leaves, {} nodes",
+            self.sah_cost, self.depth, self.leaf_count, self.node_count
+        )
+    }
+}
 
 enum BVHNode {
     Branch { left: Box<BVH>, right: Box<BVH> },
     Leaf(Box<dyn Hitable>),
 }
 
+#[allow(clippy::upper_case_acronyms)]
 pub struct BVH {
     tree: BVHNode,
     bbox: AABB,
 }
 
-impl BVH {
-    pub fn new(mut hitable: Vec<Box<dyn Hitable>>, time0: f32, time1: f32) -> Self {
-        fn box_compare(
-            time0: f32,
-            time1: f32,
-            axis: usize,
-        ) -> impl FnMut(&Box<dyn Hitable>, &Box<dyn Hitable>) -> Ordering {
-            move |a, b| {
-                let a_bbox = a.bounding_box(time0, time1);
-                let b_bbox = b.bounding_box(time0, time1);
-                if let (Some(a), Some(b)) = (a_bbox, b_bbox) {
-                    let ac = a.min[axis] + a.max[axis];
-                    let bc = b.min[axis] + b.max[axis];
-                    ac.partial_cmp(&bc).unwrap()
-                } else {
-                    panic!["no bounding box in bvh node"]
-                }
+// Per-primitive data cached once, the builders sort and split these instead of the
+// objects so `bounding_box` is never called twice for the same object.
+#[derive(Copy, Clone)]
+pub(crate) struct Primitive {
+    index: usize,
+    bbox: AABB,
+    // Twice the box centre, only ever compared.
+    centroid: Vec3,
+}
+
+// Tree shape produced by `build`, leaves refer to objects by their index in the input.
+pub(crate) enum BuildNode {
+    Leaf {
+        bbox: AABB,
+        objects: Vec<usize>,
+    },
+    Branch {
+        bbox: AABB,
+        axis: usize,
+        left: Box<BuildNode>,
+        right: Box<BuildNode>,
+    },
+}
+
+pub(crate) fn primitives(hitable: &[Box<dyn Hitable>], time0: f32, time1: f32) -> Vec<Primitive> {
+    hitable
+        .par_iter()
+        .enumerate()
+        .map(|(index, h)| {
+            let bbox = match h.bounding_box(time0, time1) {
+                Some(bbox) => bbox,
+                None => panic!["no bounding box in bvh node"],
+            };
+            Primitive {
+                index,
+                bbox,
+                centroid: bbox.min + bbox.max,
             }
-        }
+        })
+        .collect()
+}
 
-        fn axis_range(hitable: &[Box<dyn Hitable>], time0: f32, time1: f32, axis: usize) -> f32 {
-            let (min, max) = hitable
+fn sah_bin(c: f32, min: f32, max: f32) -> usize {
+    (((c - min) / (max - min) * SAH_BINS as f32) as usize).min(SAH_BINS - 1)
+}
+
+// Both splits reorder `primitives` so that the left child takes everything before the
+// returned index, and return the split axis alongside.
+fn median_split(primitives: &mut [Primitive]) -> (usize, usize) {
+    let mut axis_ranges: Vec<(usize, f32)> = (0..3)
+        .map(|axis| {
+            let (min, max) = primitives
                 .iter()
-                .fold((f32::MAX, f32::MIN), |(bmin, bmax), hit| {
-                    if let Some(aabb) = hit.bounding_box(time0, time1) {
-                        (bmin.min(aabb.min[axis]), bmax.max(aabb.max[axis]))
-                    } else {
-                        (bmin, bmax)
-                    }
+                .fold((f32::MAX, f32::MIN), |(min, max), p| {
+                    (min.min(p.bbox.min[axis]), max.max(p.bbox.max[axis]))
                 });
-            max - min
+            (axis, max - min)
+        })
+        .collect();
+
+    axis_ranges.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+    let axis = axis_ranges[0].0;
+
+    // Only the partition around the median matters, not the order within each half.
+    let mid = primitives.len() / 2;
+    primitives.select_nth_unstable_by(mid, |a, b| {
+        a.centroid[axis].partial_cmp(&b.centroid[axis]).unwrap()
+    });
+    (mid, axis)
+}
+
+// Binned SAH: primitives are sorted into buckets by centroid and every bucket boundary
+// on every axis is costed. Falls back to the median split when all centroids coincide.
+fn sah_split(primitives: &mut [Primitive]) -> (usize, usize) {
+    let mut best: Option<(f32, usize, f32, f32, usize)> = None;
+    for axis in 0..3 {
+        let (min, max) = primitives
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), p| {
+                (min.min(p.centroid[axis]), max.max(p.centroid[axis]))
+            });
+        if max - min <= 0.0 {
+            continue;
+        }
+        let mut bins: [(Option<AABB>, usize); SAH_BINS] = [(None, 0); SAH_BINS];
+        for p in primitives.iter() {
+            let bin = &mut bins[sah_bin(p.centroid[axis], min, max)];
+            bin.0 = Some(bin.0.map_or(p.bbox, |acc| surrounding_box(&acc, &p.bbox)));
+            bin.1 += 1;
         }
+        // Sweep from the right to know the cost of every right hand side.
+        let mut right_area = [0.0; SAH_BINS];
+        let mut right_count = [0; SAH_BINS];
+        let mut acc: Option<AABB> = None;
+        let mut count = 0;
+        for i in (1..SAH_BINS).rev() {
+            if let Some(b) = bins[i].0 {
+                acc = Some(acc.map_or(b, |a| surrounding_box(&a, &b)));
+            }
+            count += bins[i].1;
+            right_area[i] = acc.map_or(0.0, |a| a.surface_area());
+            right_count[i] = count;
+        }
+        let mut acc: Option<AABB> = None;
+        let mut count = 0;
+        for split in 1..SAH_BINS {
+            if let Some(b) = bins[split - 1].0 {
+                acc = Some(acc.map_or(b, |a| surrounding_box(&a, &b)));
+            }
+            count += bins[split - 1].1;
+            if count == 0 || right_count[split] == 0 {
+                continue;
+            }
+            let cost = acc.map_or(0.0, |a| a.surface_area()) * count as f32
+                + right_area[split] * right_count[split] as f32;
+            if best.is_none_or(|(best_cost, ..)| cost < best_cost) {
+                best = Some((cost, axis, min, max, split));
+            }
+        }
+    }
+
+    let (_, axis, min, max, split) = match best {
+        Some(best) => best,
+        None => return median_split(primitives),
+    };
+    // Stable partition, both halves keep their input order.
+    let (left, right): (Vec<Primitive>, Vec<Primitive>) = primitives
+        .iter()
+        .partition(|p| sah_bin(p.centroid[axis], min, max) < split);
+    primitives[..left.len()].copy_from_slice(&left);
+    primitives[left.len()..].copy_from_slice(&right);
+    (left.len(), axis)
+}
+
+// Splits down to leaves of at most `max_leaf` primitives, using the median split below
+// `max_sah_depth`. Large halves are built on the rayon pool; every subtree only depends
+// on its own primitives, so the result is the same as building serially.
+pub(crate) fn build(
+    primitives: &mut [Primitive],
+    split: SplitMethod,
+    max_leaf: usize,
+    max_sah_depth: usize,
+    depth: usize,
+) -> BuildNode {
+    let bbox = primitives[1..]
+        .iter()
+        .fold(primitives[0].bbox, |acc, p| surrounding_box(&acc, &p.bbox));
+    if primitives.len() <= max_leaf {
+        return BuildNode::Leaf {
+            bbox,
+            objects: primitives.iter().map(|p| p.index).collect(),
+        };
+    }
+    let (mid, axis) = match split {
+        SplitMethod::Sah if depth < max_sah_depth => sah_split(primitives),
+        _ => median_split(primitives),
+    };
+    let parallel = primitives.len() >= PARALLEL_BUILD_THRESHOLD;
+    let (left, right) = primitives.split_at_mut(mid);
+    let (left, right) = if parallel {
+        rayon::join(
+            || build(left, split, max_leaf, max_sah_depth, depth + 1),
+            || build(right, split, max_leaf, max_sah_depth, depth + 1),
+        )
+    } else {
+        (
+            build(left, split, max_leaf, max_sah_depth, depth + 1),
+            build(right, split, max_leaf, max_sah_depth, depth + 1),
+        )
+    };
+    BuildNode::Branch {
+        bbox,
+        axis,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
 
-        let mut axis_ranges: Vec<(usize, f32)> = (0..3)
-            .map(|a| (a, axis_range(&hitable, time0, time1, a)))
-            .collect();
+impl BVH {
+    pub fn new(hitable: Vec<Box<dyn Hitable>>, time0: f32, time1: f32) -> Self {
+        BVH::with_split(hitable, time0, time1, SplitMethod::Median)
+    }
 
-        axis_ranges.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    pub fn with_split(
+        hitable: Vec<Box<dyn Hitable>>,
+        time0: f32,
+        time1: f32,
+        split: SplitMethod,
+    ) -> Self {
+        if hitable.is_empty() {
+            panic!["no elements in scene"];
+        }
+        let mut primitives = primitives(&hitable, time0, time1);
+        let root = build(&mut primitives, split, 1, usize::MAX, 1);
+        let mut objects: Vec<Option<Box<dyn Hitable>>> = hitable.into_iter().map(Some).collect();
+        BVH::from_node(root, &mut objects)
+    }
 
-        let axis = axis_ranges[0].0;
+    fn from_node(node: BuildNode, objects: &mut [Option<Box<dyn Hitable>>]) -> Self {
+        match node {
+            BuildNode::Leaf {
+                bbox,
+                objects: leaf,
+            } => BVH {
+                tree: BVHNode::Leaf(objects[leaf[0]].take().unwrap()),
+                bbox,
+            },
+            BuildNode::Branch {
+                bbox, left, right, ..
+            } => BVH {
+                tree: BVHNode::Branch {
+                    left: Box::new(BVH::from_node(*left, objects)),
+                    right: Box::new(BVH::from_node(*right, objects)),
+                },
+                bbox,
+            },
+        }
+    }
 
-        hitable.sort_unstable_by(box_compare(time0, time1, axis));
-        let len = hitable.len();
-        match len {
-            0 => panic!["no elements in scene"],
-            1 => {
-                let leaf = hitable.pop().unwrap();
-                if let Some(bbox) = leaf.bounding_box(time0, time1) {
-                    BVH {
-                        tree: BVHNode::Leaf(leaf),
-                        bbox,
-                    }
-                } else {
-                    panic!["no bounding box in bvh node"]
+    pub fn stats(&self) -> BVHStats {
+        // Sums of the node areas, weighted by their cost, relative to the root area.
+        fn walk(node: &BVH, depth: usize, stats: &mut BVHStats) {
+            stats.node_count += 1;
+            stats.depth = stats.depth.max(depth);
+            match &node.tree {
+                BVHNode::Leaf(_) => {
+                    stats.leaf_count += 1;
+                    stats.sah_cost += INTERSECTION_COST * node.bbox.surface_area();
                 }
-            }
-            _ => {
-                let right = BVH::new(hitable.drain(len / 2..).collect(), time0, time1);
-                let left = BVH::new(hitable, time0, time1);
-                let bbox = surrounding_box(&left.bbox, &right.bbox);
-                BVH {
-                    tree: BVHNode::Branch {
-                        left: Box::new(left),
-                        right: Box::new(right),
-                    },
-                    bbox,
+                BVHNode::Branch { left, right } => {
+                    stats.sah_cost += TRAVERSAL_COST * node.bbox.surface_area();
+                    walk(left, depth + 1, stats);
+                    walk(right, depth + 1, stats);
                 }
             }
         }
+
+        let mut stats = BVHStats {
+            sah_cost: 0.0,
+            depth: 0,
+            leaf_count: 0,
+            node_count: 0,
+        };
+        walk(self, 1, &mut stats);
+        let root_area = self.bbox.surface_area();
+        if root_area > 0.0 {
+            stats.sah_cost /= root_area;
+        }
+        stats
     }
 }
 
 impl Hitable for BVH {
-    fn hit(&self, ray: &Ray, t0: f32, t1: f32) -> Option<HitRecord> {
-        match self.bbox.hit(&ray, t0, t1) {
-            Some((t_min, mut t_max)) => match &self.tree {
-                BVHNode::Leaf(leaf) => leaf.hit(&ray, t_min, t_max),
+    // Children get the ray's own interval, not the one clipped to this box: surfaces lying on
+    // the box faces would otherwise be lost to rounding in the entry and exit distances.
+    fn hit(&self, ray: &Ray, t_min: f32, mut t_max: f32) -> Option<HitRecord<'_>> {
+        match self.bbox.hit(ray, t_min, t_max) {
+            Some(_) => match &self.tree {
+                BVHNode::Leaf(leaf) => leaf.hit(ray, t_min, t_max),
                 BVHNode::Branch { left, right } => {
-                    let left = left.hit(&ray, t_min, t_max);
+                    let left = left.hit(ray, t_min, t_max);
                     if let Some(l) = &left {
                         t_max = l.t
                     };
-                    let right = right.hit(&ray, t_min, t_max);
+                    let right = right.hit(ray, t_min, t_max);
                     if right.is_some() {
                         right
                     } else {
@@ -35,12 +35,12 @@ where
     T: Hitable,
     S: Material,
 {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         self.boundary
-            .hit(r, std::f32::MIN, std::f32::MAX)
+            .hit(r, f32::MIN, f32::MAX)
             .and_then(|mut rec1| {
                 let mut rng = rand::thread_rng();
-                let rec2 = self.boundary.hit(r, rec1.t + 0.0001, std::f32::MAX);
+                let rec2 = self.boundary.hit(r, rec1.t + 0.0001, f32::MAX);
                 let mut rec2 = rec2?;
                 if rec1.t < t_min {
                     rec1.t = t_min;
@@ -0,0 +1,60 @@
+use crate::hittables::{HitRecord, Hitable};
+use crate::physics::{Ray, AABB};
+use crate::rand::prelude::*;
+use crate::textures::Texture;
+
+/// Cuts holes into an object where the red channel of `mask` says it is transparent, for
+/// leaves, fences and other geometry given by an alpha texture.
+///
+/// With a `threshold` the surface exists where the mask reaches it, without one the mask is
+/// the probability of a hit. Rays carry on past transparent points, so nothing behind them
+/// sees the holes.
+pub struct Cutout<H: Hitable, T: Texture> {
+    object: H,
+    mask: T,
+    threshold: Option<f32>,
+}
+
+impl<H: Hitable, T: Texture> Cutout<H, T> {
+    pub fn new(object: H, mask: T, threshold: f32) -> Self {
+        Cutout {
+            object,
+            mask,
+            threshold: Some(threshold),
+        }
+    }
+
+    pub fn stochastic(object: H, mask: T) -> Self {
+        Cutout {
+            object,
+            mask,
+            threshold: None,
+        }
+    }
+
+    fn opaque(&self, record: &HitRecord) -> bool {
+        let alpha = self.mask.texture(record.u, record.v, &record.p).x();
+        match self.threshold {
+            Some(threshold) => alpha >= threshold,
+            None => alpha >= 1.0 || rand::thread_rng().gen::<f32>() < alpha,
+        }
+    }
+}
+
+impl<H: Hitable, T: Texture> Hitable for Cutout<H, T> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let mut t_min = t_min;
+        loop {
+            let record = self.object.hit(r, t_min, t_max)?;
+            if self.opaque(&record) {
+                return Some(record);
+            }
+            // Objects accept hits at exactly `t_min`, so the search starts just past this one.
+            t_min = record.t.next_up();
+        }
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.object.bounding_box(t0, t1)
+    }
+}
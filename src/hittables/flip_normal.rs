@@ -1,4 +1,5 @@
-use crate::hittables::{HitRecord, Hitable};
+use crate::hittables::{HitRecord, Hitable, LightSample};
+use crate::math::Vec3;
 use crate::physics::{Ray, AABB};
 
 pub struct FlipNormal<T: Hitable> {
@@ -12,7 +13,7 @@ impl<T: Hitable> FlipNormal<T> {
 }
 
 impl<T: Hitable> Hitable for FlipNormal<T> {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         self.hittable.hit(r, t_min, t_max).map(|mut hit| {
             hit.normal = -hit.normal;
             hit
@@ -22,4 +23,12 @@ impl<T: Hitable> Hitable for FlipNormal<T> {
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
         self.hittable.bounding_box(t0, t1)
     }
+
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        self.hittable.sample(origin)
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3) -> f32 {
+        self.hittable.pdf(origin, direction)
+    }
 }
@@ -1,6 +1,7 @@
-use crate::hittables::{HitRecord, Hitable};
+use crate::hittables::{HitRecord, Hitable, LightSample};
 use crate::math::Vec3;
 use crate::physics::{surrounding_box, Ray, AABB};
+use crate::rand::prelude::*;
 
 pub struct HittableList {
     pub entities: Vec<Box<dyn Hitable>>,
@@ -18,7 +19,7 @@ impl HittableList {
 }
 
 impl Hitable for HittableList {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let mut tmp_rec: Option<HitRecord> = None;
         let mut closest_so_far = t_max;
         for hitable in self.entities.iter() {
@@ -46,4 +47,27 @@ impl Hitable for HittableList {
         }
         r_box
     }
+
+    // Picks one entity uniformly, the density is the average over all of them so that
+    // overlapping lights are accounted for.
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        let entity = self.entities.choose(&mut rand::thread_rng())?;
+        let p = entity.sample(origin)?.p;
+        Some(LightSample {
+            p,
+            pdf: self.pdf(origin, &(p - *origin)),
+        })
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3) -> f32 {
+        if self.entities.is_empty() {
+            return 0.0;
+        }
+        let sum: f32 = self
+            .entities
+            .iter()
+            .map(|entity| entity.pdf(origin, direction))
+            .sum();
+        sum / self.entities.len() as f32
+    }
 }
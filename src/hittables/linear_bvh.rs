@@ -0,0 +1,225 @@
+use crate::hittables::{
+    build, primitives, BVHStats, BuildNode, HitRecord, Hitable, SplitMethod, INTERSECTION_COST,
+    TRAVERSAL_COST,
+};
+use crate::physics::{Ray, AABB};
+
+const MAX_LEAF_OBJECTS: usize = 2;
+const STACK_SIZE: usize = 64;
+// Past this depth nodes fall back to median splits, which keeps the traversal stack bounded.
+const MAX_SAH_DEPTH: usize = STACK_SIZE / 2;
+
+// Depth-first node array: the first child of a branch directly follows it and `offset`
+// holds the index of the second child. A leaf owns `count` objects starting at `offset`.
+struct LinearNode {
+    bbox: AABB,
+    offset: u32,
+    count: u16,
+    axis: u8,
+}
+
+/// A BVH flattened into one node array and traversed with an explicit stack.
+///
+/// Children are visited front to back along the split axis of their parent, so the
+/// nearer child usually shrinks `t_max` before the farther one is tested. Leaves hold up
+/// to two objects.
+pub struct LinearBVH {
+    nodes: Vec<LinearNode>,
+    objects: Vec<Box<dyn Hitable>>,
+}
+
+impl LinearBVH {
+    pub fn new(hitable: Vec<Box<dyn Hitable>>, time0: f32, time1: f32, split: SplitMethod) -> Self {
+        if hitable.is_empty() {
+            panic!["no elements in scene"];
+        }
+        let mut primitives = primitives(&hitable, time0, time1);
+        let root = build(&mut primitives, split, MAX_LEAF_OBJECTS, MAX_SAH_DEPTH, 1);
+        let mut bvh = LinearBVH {
+            nodes: Vec::with_capacity(2 * hitable.len() / MAX_LEAF_OBJECTS + 1),
+            objects: Vec::with_capacity(hitable.len()),
+        };
+        let mut objects: Vec<Option<Box<dyn Hitable>>> = hitable.into_iter().map(Some).collect();
+        bvh.flatten(root, &mut objects);
+        bvh
+    }
+
+    fn flatten(&mut self, node: BuildNode, objects: &mut [Option<Box<dyn Hitable>>]) {
+        let index = self.nodes.len();
+        match node {
+            BuildNode::Leaf {
+                bbox,
+                objects: leaf,
+            } => {
+                self.nodes.push(LinearNode {
+                    bbox,
+                    offset: self.objects.len() as u32,
+                    count: leaf.len() as u16,
+                    axis: 0,
+                });
+                self.objects
+                    .extend(leaf.into_iter().map(|i| objects[i].take().unwrap()));
+            }
+            BuildNode::Branch {
+                bbox,
+                axis,
+                left,
+                right,
+            } => {
+                self.nodes.push(LinearNode {
+                    bbox,
+                    offset: 0,
+                    count: 0,
+                    axis: axis as u8,
+                });
+                self.flatten(*left, objects);
+                self.nodes[index].offset = self.nodes.len() as u32;
+                self.flatten(*right, objects);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> BVHStats {
+        let mut stats = BVHStats {
+            sah_cost: 0.0,
+            depth: 0,
+            leaf_count: 0,
+            node_count: self.nodes.len(),
+        };
+        let mut stack = vec![(0, 1)];
+        while let Some((index, depth)) = stack.pop() {
+            let node = &self.nodes[index];
+            stats.depth = stats.depth.max(depth);
+            if node.count > 0 {
+                stats.leaf_count += 1;
+                stats.sah_cost += INTERSECTION_COST * node.count as f32 * node.bbox.surface_area();
+            } else {
+                stats.sah_cost += TRAVERSAL_COST * node.bbox.surface_area();
+                stack.push((index + 1, depth + 1));
+                stack.push((node.offset as usize, depth + 1));
+            }
+        }
+        let root_area = self.nodes[0].bbox.surface_area();
+        if root_area > 0.0 {
+            stats.sah_cost /= root_area;
+        }
+        stats
+    }
+}
+
+impl Hitable for LinearBVH {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let negative = [
+            r.direction.x() < 0.0,
+            r.direction.y() < 0.0,
+            r.direction.z() < 0.0,
+        ];
+        let mut closest: Option<HitRecord> = None;
+        let mut closest_so_far = t_max;
+        let mut stack = [0usize; STACK_SIZE];
+        let mut top = 0;
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            if node.bbox.hit(r, t_min, closest_so_far).is_some() {
+                if node.count > 0 {
+                    let start = node.offset as usize;
+                    for object in self.objects[start..start + node.count as usize].iter() {
+                        if let Some(rec) = object.hit(r, t_min, closest_so_far) {
+                            closest_so_far = rec.t;
+                            closest = Some(rec);
+                        }
+                    }
+                } else {
+                    // Visit the child on the near side of the split first.
+                    let (near, far) = if negative[node.axis as usize] {
+                        (node.offset as usize, index + 1)
+                    } else {
+                        (index + 1, node.offset as usize)
+                    };
+                    stack[top] = far;
+                    top += 1;
+                    index = near;
+                    continue;
+                }
+            }
+            if top == 0 {
+                break;
+            }
+            top -= 1;
+            index = stack[top];
+        }
+        closest
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        Some(self.nodes[0].bbox)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::{BoxShape, Sphere, BVH};
+    use crate::loaders::test_material;
+    use crate::math::Vec3;
+    use rand::prelude::*;
+
+    // A grid of boxes sharing their side faces, like `box_grid`, with spheres scattered above.
+    fn objects() -> Vec<Box<dyn Hitable>> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut objects: Vec<Box<dyn Hitable>> = Vec::new();
+        for i in 0..12 {
+            for j in 0..12 {
+                let min = Vec3::new(i as f32, 0.0, j as f32);
+                let max = min + Vec3::new(1.0, 0.5 + rng.gen::<f32>(), 1.0);
+                objects.push(Box::new(BoxShape::new(min, max, test_material())));
+            }
+        }
+        for _ in 0..100 {
+            let center = Vec3::new(
+                12.0 * rng.gen::<f32>(),
+                2.0 + rng.gen::<f32>(),
+                12.0 * rng.gen::<f32>(),
+            );
+            objects.push(Box::new(Sphere::new(
+                0.3 * rng.gen::<f32>(),
+                center,
+                test_material(),
+            )));
+        }
+        objects
+    }
+
+    #[test]
+    fn boxed_and_linear_layouts_find_the_same_hits() {
+        for split in [SplitMethod::Median, SplitMethod::Sah] {
+            let boxed = BVH::with_split(objects(), 0.0, 1.0, split);
+            let linear = LinearBVH::new(objects(), 0.0, 1.0, split);
+            let mut rng = StdRng::seed_from_u64(11);
+            let mut hits = 0;
+            for _ in 0..20_000 {
+                let origin = Vec3::new(
+                    -5.0 + 22.0 * rng.gen::<f32>(),
+                    4.0 + 4.0 * rng.gen::<f32>(),
+                    -5.0 + 22.0 * rng.gen::<f32>(),
+                );
+                let target = Vec3::new(
+                    12.0 * rng.gen::<f32>(),
+                    2.0 * rng.gen::<f32>(),
+                    12.0 * rng.gen::<f32>(),
+                );
+                let ray = Ray::new(origin, target - origin, 0.0);
+                let expected = boxed.hit(&ray, 0.001, f32::MAX).map(|rec| rec.t);
+                let actual = linear.hit(&ray, 0.001, f32::MAX).map(|rec| rec.t);
+                assert_eq!(
+                    expected, actual,
+                    "{:?} split, ray from {:?} to {:?}",
+                    split, origin, target
+                );
+                hits += expected.is_some() as usize;
+            }
+            assert!(hits > 10_000, "only {} hits", hits);
+        }
+    }
+}
@@ -1,22 +1,28 @@
-use crate::math::Vec3;
+use crate::math::{Onb, Vec3};
 use crate::physics::{Material, Ray, AABB};
 
 mod box_shape;
 mod bvh;
 mod constant_medium;
+mod cutout;
 mod flip_normal;
 mod hittable_list;
+mod linear_bvh;
 mod moving_sphere;
 mod rotate_y;
 mod sphere;
+mod transform;
 mod translate;
+mod triangle;
+mod triangle_mesh;
 mod xy_rect;
 mod xz_rect;
 mod yz_rect;
 
 pub use self::{
-    box_shape::*, bvh::*, constant_medium::*, flip_normal::*, hittable_list::*, moving_sphere::*,
-    rotate_y::*, sphere::*, translate::*, xy_rect::*, xz_rect::*, yz_rect::*,
+    box_shape::*, bvh::*, constant_medium::*, cutout::*, flip_normal::*, hittable_list::*,
+    linear_bvh::*, moving_sphere::*, rotate_y::*, sphere::*, transform::*, translate::*,
+    triangle::*, triangle_mesh::*, xy_rect::*, xz_rect::*, yz_rect::*,
 };
 
 #[derive(Copy, Clone)]
@@ -27,6 +33,9 @@ pub struct HitRecord<'a> {
     pub material: &'a dyn Material,
     pub u: f32,
     pub v: f32,
+    /// Derivatives of `p` along `u` and `v`, zero when the surface does not provide them.
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
 }
 
 impl<'a> HitRecord<'a> {
@@ -38,11 +47,75 @@ impl<'a> HitRecord<'a> {
             material,
             u,
             v,
+            dpdu: Vec3::default(),
+            dpdv: Vec3::default(),
         }
     }
+
+    pub fn with_tangents(mut self, dpdu: Vec3, dpdv: Vec3) -> Self {
+        self.dpdu = dpdu;
+        self.dpdv = dpdv;
+        self
+    }
+
+    /// `dpdu` and `dpdv`, or an arbitrary pair of unit tangents for surfaces without them.
+    pub fn tangents(&self) -> (Vec3, Vec3) {
+        if self.dpdu.squared_length() > 0.0 && self.dpdv.squared_length() > 0.0 {
+            (self.dpdu, self.dpdv)
+        } else {
+            let onb = Onb::new(self.normal);
+            (onb.u, onb.v)
+        }
+    }
+}
+
+/// A point picked on a light, `pdf` is its density per unit solid angle seen from the origin.
+pub struct LightSample {
+    pub p: Vec3,
+    pub pdf: f32,
+}
+
+// Converts an area density of `1 / area` at `p` to a density per unit solid angle at `origin`.
+pub(crate) fn solid_angle_pdf(origin: &Vec3, p: &Vec3, normal: &Vec3, area: f32) -> f32 {
+    let d = *p - *origin;
+    let distance_squared = d.squared_length();
+    let cosine = (d.dot(*normal) / distance_squared.sqrt()).abs();
+    if cosine <= 0.0 {
+        0.0
+    } else {
+        distance_squared / (cosine * area)
+    }
 }
 
 pub trait Hitable: Sync {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>>;
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB>;
+
+    // Light sampling, only shapes that can be used as lights implement these.
+    fn sample(&self, _origin: &Vec3) -> Option<LightSample> {
+        None
+    }
+
+    // Density of `sample` producing `direction` from `origin`.
+    fn pdf(&self, _origin: &Vec3, _direction: &Vec3) -> f32 {
+        0.0
+    }
+}
+
+impl<T: Hitable + ?Sized> Hitable for Box<T> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        (**self).hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        (**self).bounding_box(t0, t1)
+    }
+
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        (**self).sample(origin)
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3) -> f32 {
+        (**self).pdf(origin, direction)
+    }
 }
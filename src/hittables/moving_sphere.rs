@@ -41,7 +41,7 @@ impl<T: Material> MovingSphere<T> {
 }
 
 impl<T: Material> Hitable for MovingSphere<T> {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let radius = self.r;
         let center = self.center(r.time);
         let oc = r.origin - center;
@@ -16,8 +16,8 @@ impl<T: Hitable> RotateY<T> {
         let sin_theta = radians.sin();
         let cos_theta = radians.cos();
         let aabb = object.bounding_box(0.0, 1.0);
-        let mut min = Vec3::new(std::f32::MAX, std::f32::MAX, std::f32::MAX);
-        let mut max = Vec3::new(std::f32::MIN, std::f32::MIN, std::f32::MIN);
+        let mut min = Vec3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Vec3::new(f32::MIN, f32::MIN, f32::MIN);
         let aabb = match aabb {
             Some(bbox) => {
                 for i in 0..2 {
@@ -53,8 +53,19 @@ impl<T: Hitable> RotateY<T> {
     }
 }
 
+impl<T: Hitable> RotateY<T> {
+    // From the object's frame back to the world.
+    fn rotate_back(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x() + self.sin_theta * v.z(),
+            v.y(),
+            -self.sin_theta * v.x() + self.cos_theta * v.z(),
+        )
+    }
+}
+
 impl<T: Hitable> Hitable for RotateY<T> {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let origin = Vec3::new(
             self.cos_theta * r.origin.x() - self.sin_theta * r.origin.z(),
             r.origin.y(),
@@ -70,20 +81,10 @@ impl<T: Hitable> Hitable for RotateY<T> {
         self.object
             .hit(&rotated_ray, t_min, t_max)
             .map(|mut hit_record| {
-                let p = hit_record.p;
-                let p = Vec3::new(
-                    self.cos_theta * p.x() + self.sin_theta * p.z(),
-                    p.y(),
-                    -self.sin_theta * p.x() + self.cos_theta * p.z(),
-                );
-                let normal = hit_record.normal;
-                let normal = Vec3::new(
-                    self.cos_theta * normal.x() + self.sin_theta * normal.z(),
-                    normal.y(),
-                    -self.sin_theta * normal.x() + self.cos_theta * normal.z(),
-                );
-                hit_record.p = p;
-                hit_record.normal = normal;
+                hit_record.p = self.rotate_back(&hit_record.p);
+                hit_record.normal = self.rotate_back(&hit_record.normal);
+                hit_record.dpdu = self.rotate_back(&hit_record.dpdu);
+                hit_record.dpdv = self.rotate_back(&hit_record.dpdv);
                 hit_record
             })
     }
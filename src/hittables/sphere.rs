@@ -1,5 +1,6 @@
-use crate::hittables::{HitRecord, Hitable};
-use crate::math::{dot, Vec3};
+use crate::hittables::{solid_angle_pdf, HitRecord, Hitable, LightSample};
+use crate::math::sampling::{uniform_cone, uniform_cone_pdf, uniform_sphere};
+use crate::math::{dot, Onb, Vec3};
 use crate::physics::{Material, Ray, AABB};
 use std::f32::consts::PI;
 
@@ -19,6 +20,20 @@ impl<T: Material> Sphere<T> {
         }
     }
 
+    fn area(&self) -> f32 {
+        4.0 * PI * self.r * self.r
+    }
+
+    // Cosine of the half angle the sphere subtends from `origin`, `None` from inside.
+    fn cone(&self, origin: &Vec3) -> Option<f32> {
+        let distance_squared = (self.center - *origin).squared_length();
+        let radius_squared = self.r * self.r;
+        if distance_squared <= radius_squared {
+            return None;
+        }
+        Some((1.0 - radius_squared / distance_squared).sqrt())
+    }
+
     fn get_uv(&self, p: &Vec3) -> (f32, f32) {
         let p = (*p - self.center) / self.r;
         let phi = p.z().atan2(p.x());
@@ -29,8 +44,25 @@ impl<T: Material> Sphere<T> {
     }
 }
 
+// Derivatives of the sphere's `get_uv` parametrisation at `offset` from its center. Zero at
+// the poles where `u` is undefined.
+pub(crate) fn sphere_tangents(offset: &Vec3) -> (Vec3, Vec3) {
+    let rho = (offset.x() * offset.x() + offset.z() * offset.z()).sqrt();
+    if rho == 0.0 {
+        return (Vec3::default(), Vec3::default());
+    }
+    let dpdu = 2.0 * PI * Vec3::new(offset.z(), 0.0, -offset.x());
+    let dpdv = PI
+        * Vec3::new(
+            -offset.y() * offset.x() / rho,
+            rho,
+            -offset.y() * offset.z() / rho,
+        );
+    (dpdu, dpdv)
+}
+
 impl<T: Material> Hitable for Sphere<T> {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let radius = self.r;
         let center = self.center;
         let oc = r.origin - center;
@@ -44,14 +76,20 @@ impl<T: Material> Hitable for Sphere<T> {
                 let p = r.point_at_parameter(temp);
                 let normal = (p - center) / radius;
                 let (u, v) = self.get_uv(&p);
-                return Some(HitRecord::new(temp, p, normal, &self.material, u, v));
+                let (dpdu, dpdv) = sphere_tangents(&(p - center));
+                return Some(
+                    HitRecord::new(temp, p, normal, &self.material, u, v).with_tangents(dpdu, dpdv),
+                );
             }
             let temp = (-b + (b * b - a * c).sqrt()) / a;
             if temp < t_max && temp > t_min {
                 let p = r.point_at_parameter(temp);
                 let normal = (p - center) / radius;
                 let (u, v) = self.get_uv(&p);
-                return Some(HitRecord::new(temp, p, normal, &self.material, u, v));
+                let (dpdu, dpdv) = sphere_tangents(&(p - center));
+                return Some(
+                    HitRecord::new(temp, p, normal, &self.material, u, v).with_tangents(dpdu, dpdv),
+                );
             }
         }
         None
@@ -63,4 +101,38 @@ impl<T: Material> Hitable for Sphere<T> {
             self.center + Vec3::new(self.r, self.r, self.r),
         ))
     }
+
+    // Uniform over the cone the sphere subtends from outside, uniform over the surface
+    // from inside.
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        match self.cone(origin) {
+            Some(cos_theta_max) => {
+                let local = uniform_cone(cos_theta_max);
+                let direction = Onb::new(self.center - *origin).local_to_world(&local);
+                let rec = self.hit(&Ray::new(*origin, direction, 0.0), 0.001, f32::MAX)?;
+                Some(LightSample {
+                    p: rec.p,
+                    pdf: uniform_cone_pdf(cos_theta_max),
+                })
+            }
+            None => {
+                let normal = uniform_sphere();
+                let p = self.center + normal * self.r;
+                Some(LightSample {
+                    p,
+                    pdf: solid_angle_pdf(origin, &p, &normal, self.area()),
+                })
+            }
+        }
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3) -> f32 {
+        match self.hit(&Ray::new(*origin, *direction, 0.0), 0.001, f32::MAX) {
+            Some(rec) => match self.cone(origin) {
+                Some(cos_theta_max) => uniform_cone_pdf(cos_theta_max),
+                None => solid_angle_pdf(origin, &rec.p, &rec.normal, self.area()),
+            },
+            None => 0.0,
+        }
+    }
 }
@@ -0,0 +1,102 @@
+use crate::hittables::{HitRecord, Hitable};
+use crate::math::{Matrix4, Vec3};
+use crate::physics::{Ray, AABB};
+
+/// Instances `object` under an arbitrary affine transform from object to world space.
+///
+/// Rays are moved into object space without renormalising the direction, so `t` is the
+/// same in both spaces. Normals go back through the inverse-transpose.
+pub struct Transform<T: Hitable> {
+    object: T,
+    matrix: Matrix4,
+    inverse: Matrix4,
+    normal_matrix: Matrix4,
+}
+
+impl<T: Hitable> Transform<T> {
+    /// `None` when `matrix` can't be inverted, e.g. for a zero scale.
+    pub fn new(object: T, matrix: Matrix4) -> Option<Self> {
+        let inverse = matrix.inverse()?;
+        Some(Transform {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+        })
+    }
+
+    // Every builder applies its transform after the ones already composed, and gives `None`
+    // once the composition stops being invertible.
+    pub fn then(self, matrix: Matrix4) -> Option<Self> {
+        Transform::new(self.object, matrix * self.matrix)
+    }
+
+    pub fn translate(self, offset: Vec3) -> Option<Self> {
+        self.then(Matrix4::translation(offset))
+    }
+
+    pub fn scale(self, scale: Vec3) -> Option<Self> {
+        self.then(Matrix4::scaling(scale))
+    }
+
+    pub fn rotate_x(self, angle: f32) -> Option<Self> {
+        self.then(Matrix4::rotation_x(angle))
+    }
+
+    pub fn rotate_y(self, angle: f32) -> Option<Self> {
+        self.then(Matrix4::rotation_y(angle))
+    }
+
+    pub fn rotate_z(self, angle: f32) -> Option<Self> {
+        self.then(Matrix4::rotation_z(angle))
+    }
+
+    pub fn rotate(self, axis: Vec3, angle: f32) -> Option<Self> {
+        self.then(Matrix4::rotation(axis, angle))
+    }
+
+    pub fn look_at(self, from: Vec3, to: Vec3, up: Vec3) -> Option<Self> {
+        self.then(Matrix4::look_at(from, to, up)?)
+    }
+}
+
+impl<T: Hitable> Hitable for Transform<T> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let local = Ray::new(
+            self.inverse.transform_point(&r.origin),
+            self.inverse.transform_vector(&r.direction),
+            r.time,
+        );
+        self.object.hit(&local, t_min, t_max).map(|mut hit_record| {
+            hit_record.p = self.matrix.transform_point(&hit_record.p);
+            hit_record.dpdu = self.matrix.transform_vector(&hit_record.dpdu);
+            hit_record.dpdv = self.matrix.transform_vector(&hit_record.dpdv);
+            hit_record.normal = self
+                .normal_matrix
+                .transform_vector(&hit_record.normal)
+                .unit_vector();
+            hit_record
+        })
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        let bbox = self.object.bounding_box(t0, t1)?;
+        let mut min = Vec3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Vec3::new(f32::MIN, f32::MIN, f32::MIN);
+        for i in 0..2 {
+            for j in 0..2 {
+                for k in 0..2 {
+                    let x = i as f32 * bbox.max.x() + (1 - i) as f32 * bbox.min.x();
+                    let y = j as f32 * bbox.max.y() + (1 - j) as f32 * bbox.min.y();
+                    let z = k as f32 * bbox.max.z() + (1 - k) as f32 * bbox.min.z();
+                    let tester = self.matrix.transform_point(&Vec3::new(x, y, z));
+                    for c in 0..3 {
+                        min[c] = min[c].min(tester[c]);
+                        max[c] = max[c].max(tester[c]);
+                    }
+                }
+            }
+        }
+        Some(AABB::new(min, max))
+    }
+}
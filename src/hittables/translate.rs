@@ -1,4 +1,4 @@
-use crate::hittables::{HitRecord, Hitable};
+use crate::hittables::{HitRecord, Hitable, LightSample};
 use crate::math::Vec3;
 use crate::physics::{Ray, AABB};
 
@@ -14,7 +14,7 @@ impl<T: Hitable> Translate<T> {
 }
 
 impl<T: Hitable> Hitable for Translate<T> {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let moved_ray = Ray::new(r.origin - self.offset, r.direction, r.time);
         self.object
             .hit(&moved_ray, t_min, t_max)
@@ -28,4 +28,17 @@ impl<T: Hitable> Hitable for Translate<T> {
         let aabb = self.object.bounding_box(t0, t1);
         aabb.map(|b| AABB::new(b.min + self.offset, b.max + self.offset))
     }
+
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        self.object
+            .sample(&(*origin - self.offset))
+            .map(|sample| LightSample {
+                p: sample.p + self.offset,
+                pdf: sample.pdf,
+            })
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3) -> f32 {
+        self.object.pdf(&(*origin - self.offset), direction)
+    }
 }
@@ -0,0 +1,103 @@
+use crate::hittables::{HitRecord, Hitable};
+use crate::math::{dot, Vec3};
+use crate::physics::{Material, Ray, AABB};
+
+const EPSILON: f32 = 1e-8;
+
+// Möller–Trumbore intersection, returns `t` and the barycentric weights of `p1` and `p2`.
+#[inline]
+pub(crate) fn intersect_triangle(
+    r: &Ray,
+    p0: Vec3,
+    p1: Vec3,
+    p2: Vec3,
+    t_min: f32,
+    t_max: f32,
+) -> Option<(f32, f32, f32)> {
+    let edge1 = p1 - p0;
+    let edge2 = p2 - p0;
+    let pvec = r.direction.cross(edge2);
+    let det = dot(&edge1, &pvec);
+    if det.abs() < EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let tvec = r.origin - p0;
+    let b1 = dot(&tvec, &pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let qvec = tvec.cross(edge1);
+    let b2 = dot(&r.direction, &qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+    let t = dot(&edge2, &qvec) * inv_det;
+    if t < t_min || t > t_max {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+// Flat triangles get a tiny thickness so that the slab test never collapses.
+pub(crate) fn triangle_box(p0: Vec3, p1: Vec3, p2: Vec3) -> AABB {
+    let mut min = p0;
+    let mut max = p0;
+    for p in [p1, p2].iter() {
+        for a in 0..3 {
+            min[a] = min[a].min(p[a]);
+            max[a] = max[a].max(p[a]);
+        }
+    }
+    for a in 0..3 {
+        if max[a] - min[a] < 0.0001 {
+            min[a] -= 0.0001;
+            max[a] += 0.0001;
+        }
+    }
+    AABB::new(min, max)
+}
+
+pub struct Triangle<T: Material> {
+    vertices: [Vec3; 3],
+    normal: Vec3,
+    material: T,
+}
+
+impl<T: Material> Triangle<T> {
+    /// `None` for degenerate triangles, which have no normal.
+    pub fn new(v0: Vec3, v1: Vec3, v2: Vec3, material: T) -> Option<Self> {
+        let normal = (v1 - v0).cross(v2 - v0);
+        if !(normal.squared_length() > 0.0 && normal.is_finite()) {
+            return None;
+        }
+        Some(Triangle {
+            vertices: [v0, v1, v2],
+            normal: normal.unit_vector(),
+            material,
+        })
+    }
+}
+
+impl<T: Material> Hitable for Triangle<T> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let [p0, p1, p2] = self.vertices;
+        let (t, b1, b2) = intersect_triangle(r, p0, p1, p2, t_min, t_max)?;
+        Some(
+            HitRecord::new(
+                t,
+                r.point_at_parameter(t),
+                self.normal,
+                &self.material,
+                b1,
+                b2,
+            )
+            .with_tangents(p1 - p0, p2 - p0),
+        )
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        let [p0, p1, p2] = self.vertices;
+        Some(triangle_box(p0, p1, p2))
+    }
+}
@@ -0,0 +1,261 @@
+use crate::hittables::{intersect_triangle, triangle_box, HitRecord, Hitable};
+use crate::math::Vec3;
+use crate::physics::{surrounding_box, Material, Ray, AABB};
+
+const MAX_LEAF_TRIANGLES: usize = 4;
+// Nodes this deep become leaves, so the traversal stack never holds more than this.
+const STACK_SIZE: usize = 64;
+
+// Nodes are stored depth first: the left child of a branch directly follows it,
+// `offset` points at the right child. Leaves reference `count` triangles starting at `offset`.
+struct MeshNode {
+    bbox: AABB,
+    offset: u32,
+    count: u32,
+}
+
+/// An indexed triangle mesh sharing one vertex buffer between all of its triangles.
+///
+/// `normals` and `uvs` are either empty or hold one entry per position. Without normals the
+/// geometric normal is used, without UVs the barycentric coordinates end up in `u`/`v`.
+pub struct TriangleMesh<T: Material> {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f32, f32)>,
+    indices: Vec<[u32; 3]>,
+    nodes: Vec<MeshNode>,
+    material: T,
+}
+
+impl<T: Material> TriangleMesh<T> {
+    /// Fails on meshes with mismatched attributes, out-of-range indices or non-finite
+    /// positions. Degenerate triangles are dropped, an error only when none are left.
+    pub fn new(
+        positions: Vec<Vec3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(f32, f32)>,
+        indices: Vec<[u32; 3]>,
+        material: T,
+    ) -> Result<Self, String> {
+        if indices.is_empty() {
+            return Err(String::from("no triangles in mesh"));
+        }
+        if !normals.is_empty() && normals.len() != positions.len() {
+            return Err(String::from("expected one normal per position"));
+        }
+        if !uvs.is_empty() && uvs.len() != positions.len() {
+            return Err(String::from("expected one uv per position"));
+        }
+        if let Some(&i) = indices
+            .iter()
+            .flatten()
+            .find(|&&i| i as usize >= positions.len())
+        {
+            return Err(format!("vertex index {} out of range", i));
+        }
+        if let Some(i) = positions.iter().position(|p| !p.is_finite()) {
+            return Err(format!("vertex {} is not finite", i));
+        }
+        let indices: Vec<[u32; 3]> = indices
+            .into_iter()
+            .filter(|&[i0, i1, i2]| {
+                let (p0, p1, p2) = (
+                    positions[i0 as usize],
+                    positions[i1 as usize],
+                    positions[i2 as usize],
+                );
+                (p1 - p0).cross(p2 - p0).squared_length() > 0.0
+            })
+            .collect();
+        if indices.is_empty() {
+            return Err(String::from("every triangle in the mesh is degenerate"));
+        }
+        let mut mesh = TriangleMesh {
+            positions,
+            normals,
+            uvs,
+            indices,
+            nodes: Vec::new(),
+            material,
+        };
+        mesh.build_tree();
+        Ok(mesh)
+    }
+
+    fn corners(&self, triangle: usize) -> (Vec3, Vec3, Vec3) {
+        let [i0, i1, i2] = self.indices[triangle];
+        (
+            self.positions[i0 as usize],
+            self.positions[i1 as usize],
+            self.positions[i2 as usize],
+        )
+    }
+
+    fn build_tree(&mut self) {
+        let boxes: Vec<AABB> = (0..self.indices.len())
+            .map(|i| {
+                let (p0, p1, p2) = self.corners(i);
+                triangle_box(p0, p1, p2)
+            })
+            .collect();
+        let mut order: Vec<u32> = (0..self.indices.len() as u32).collect();
+        let mut nodes = Vec::with_capacity(2 * order.len() / MAX_LEAF_TRIANGLES + 1);
+        build_node(&mut nodes, &boxes, &mut order, 0, 0);
+        // Reorder the triangles so that every leaf covers a contiguous range.
+        self.indices = order.iter().map(|&i| self.indices[i as usize]).collect();
+        self.nodes = nodes;
+    }
+
+    fn hit_triangle(
+        &self,
+        r: &Ray,
+        triangle: usize,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<HitRecord<'_>> {
+        let (p0, p1, p2) = self.corners(triangle);
+        let (t, b1, b2) = intersect_triangle(r, p0, p1, p2, t_min, t_max)?;
+        let b0 = 1.0 - b1 - b2;
+        let [i0, i1, i2] = self.indices[triangle];
+        let (i0, i1, i2) = (i0 as usize, i1 as usize, i2 as usize);
+        // Interpolated normals that cancel out, e.g. from zero normals in the file, fall back
+        // to the face normal.
+        let shading = if self.normals.is_empty() {
+            Vec3::default()
+        } else {
+            b0 * self.normals[i0] + b1 * self.normals[i1] + b2 * self.normals[i2]
+        };
+        let normal = if shading.squared_length() > 0.0 {
+            shading.unit_vector()
+        } else {
+            (p1 - p0).cross(p2 - p0).unit_vector()
+        };
+        let (u, v, dpdu, dpdv) = if self.uvs.is_empty() {
+            (b1, b2, p1 - p0, p2 - p0)
+        } else {
+            let (uv0, uv1, uv2) = (self.uvs[i0], self.uvs[i1], self.uvs[i2]);
+            let (dpdu, dpdv) = uv_tangents(p0, p1, p2, uv0, uv1, uv2);
+            (
+                b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+                b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+                dpdu,
+                dpdv,
+            )
+        };
+        Some(
+            HitRecord::new(t, r.point_at_parameter(t), normal, &self.material, u, v)
+                .with_tangents(dpdu, dpdv),
+        )
+    }
+}
+
+// Solves for the derivatives of position along the texture coordinates of a triangle, zero
+// when the UVs are degenerate.
+fn uv_tangents(
+    p0: Vec3,
+    p1: Vec3,
+    p2: Vec3,
+    uv0: (f32, f32),
+    uv1: (f32, f32),
+    uv2: (f32, f32),
+) -> (Vec3, Vec3) {
+    let (du02, dv02) = (uv0.0 - uv2.0, uv0.1 - uv2.1);
+    let (du12, dv12) = (uv1.0 - uv2.0, uv1.1 - uv2.1);
+    let determinant = du02 * dv12 - dv02 * du12;
+    if determinant.abs() < 1e-9 {
+        return (Vec3::default(), Vec3::default());
+    }
+    let (dp02, dp12) = (p0 - p2, p1 - p2);
+    let dpdu = (dv12 * dp02 - dv02 * dp12) / determinant;
+    let dpdv = (du02 * dp12 - du12 * dp02) / determinant;
+    (dpdu, dpdv)
+}
+
+fn build_node(
+    nodes: &mut Vec<MeshNode>,
+    boxes: &[AABB],
+    order: &mut [u32],
+    offset: usize,
+    depth: usize,
+) -> AABB {
+    let bbox = order[1..].iter().fold(boxes[order[0] as usize], |acc, &i| {
+        surrounding_box(&acc, &boxes[i as usize])
+    });
+    let index = nodes.len();
+    nodes.push(MeshNode {
+        bbox,
+        offset: offset as u32,
+        count: order.len() as u32,
+    });
+    if order.len() <= MAX_LEAF_TRIANGLES || depth >= STACK_SIZE {
+        return bbox;
+    }
+
+    let centroid = |i: u32, axis: usize| {
+        let b = &boxes[i as usize];
+        b.min[axis] + b.max[axis]
+    };
+    let axis = (0..3)
+        .map(|axis| {
+            let (min, max) = order.iter().fold((f32::MAX, f32::MIN), |(min, max), &i| {
+                let c = centroid(i, axis);
+                (min.min(c), max.max(c))
+            });
+            (axis, max - min)
+        })
+        .fold(
+            (0, f32::MIN),
+            |best, range| if range.1 > best.1 { range } else { best },
+        )
+        .0;
+    let mid = order.len() / 2;
+    order.select_nth_unstable_by(mid, |&a, &b| {
+        centroid(a, axis).total_cmp(&centroid(b, axis))
+    });
+
+    let (left, right) = order.split_at_mut(mid);
+    build_node(nodes, boxes, left, offset, depth + 1);
+    let right_index = nodes.len();
+    build_node(nodes, boxes, right, offset + mid, depth + 1);
+    nodes[index].offset = right_index as u32;
+    nodes[index].count = 0;
+    bbox
+}
+
+impl<T: Material> Hitable for TriangleMesh<T> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let mut closest: Option<HitRecord> = None;
+        let mut closest_so_far = t_max;
+        let mut stack = [0usize; STACK_SIZE];
+        let mut top = 0;
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            if node.bbox.hit(r, t_min, closest_so_far).is_some() {
+                if node.count == 0 {
+                    stack[top] = node.offset as usize;
+                    top += 1;
+                    index += 1;
+                    continue;
+                }
+                let start = node.offset as usize;
+                for triangle in start..start + node.count as usize {
+                    if let Some(rec) = self.hit_triangle(r, triangle, t_min, closest_so_far) {
+                        closest_so_far = rec.t;
+                        closest = Some(rec);
+                    }
+                }
+            }
+            if top == 0 {
+                break;
+            }
+            top -= 1;
+            index = stack[top];
+        }
+        closest
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        Some(self.nodes[0].bbox)
+    }
+}
@@ -1,6 +1,7 @@
-use crate::hittables::{HitRecord, Hitable};
+use crate::hittables::{solid_angle_pdf, HitRecord, Hitable, LightSample};
 use crate::math::Vec3;
 use crate::physics::{Material, Ray, AABB};
+use crate::rand::prelude::*;
 
 pub struct XZRect<T: Material> {
     x0: f32,
@@ -22,10 +23,14 @@ impl<T: Material> XZRect<T> {
             material,
         }
     }
+
+    fn area(&self) -> f32 {
+        (self.x1 - self.x0) * (self.z1 - self.z0)
+    }
 }
 
 impl<T: Material> Hitable for XZRect<T> {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let t = (self.k - r.origin.y()) / r.direction.y();
         if t < t_min || t > t_max {
             return None;
@@ -35,14 +40,20 @@ impl<T: Material> Hitable for XZRect<T> {
         if x < self.x0 || x > self.x1 || z < self.z0 || z > self.z1 {
             return None;
         }
-        Some(HitRecord::new(
-            t,
-            r.point_at_parameter(t),
-            Vec3::new(0.0, 1.0, 0.0),
-            &self.material,
-            (x - self.x0) / (self.x1 - self.x0),
-            (z - self.z0) / (self.z1 - self.z0),
-        ))
+        Some(
+            HitRecord::new(
+                t,
+                r.point_at_parameter(t),
+                Vec3::new(0.0, 1.0, 0.0),
+                &self.material,
+                (x - self.x0) / (self.x1 - self.x0),
+                (z - self.z0) / (self.z1 - self.z0),
+            )
+            .with_tangents(
+                Vec3::new(self.x1 - self.x0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, self.z1 - self.z0),
+            ),
+        )
     }
 
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
@@ -51,4 +62,25 @@ impl<T: Material> Hitable for XZRect<T> {
             Vec3::new(self.x1, self.k + 0.0001, self.z1),
         ))
     }
+
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        let mut rng = rand::thread_rng();
+        let p = Vec3::new(
+            self.x0 + rng.gen::<f32>() * (self.x1 - self.x0),
+            self.k,
+            self.z0 + rng.gen::<f32>() * (self.z1 - self.z0),
+        );
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        Some(LightSample {
+            p,
+            pdf: solid_angle_pdf(origin, &p, &normal, self.area()),
+        })
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3) -> f32 {
+        match self.hit(&Ray::new(*origin, *direction, 0.0), 0.001, f32::MAX) {
+            Some(rec) => solid_angle_pdf(origin, &rec.p, &rec.normal, self.area()),
+            None => 0.0,
+        }
+    }
 }
@@ -0,0 +1,55 @@
+use crate::hittables::Hitable;
+use crate::integrators::{Integrator, PathStats};
+use crate::math::sampling::{
+    cosine_hemisphere, cosine_hemisphere_pdf, uniform_hemisphere, uniform_hemisphere_pdf,
+};
+use crate::math::{Onb, Vec3};
+use crate::physics::Ray;
+use crate::scene::Scene;
+use std::f32::consts::PI;
+
+/// Cosine weighted fraction of the hemisphere above a hit that is free of occluders within
+/// `distance`. Probe rays follow the cosine, or are uniform when `cosine_sampling` is off.
+pub struct AmbientOcclusion {
+    distance: f32,
+    cosine_sampling: bool,
+}
+
+impl AmbientOcclusion {
+    pub fn new(distance: f32, cosine_sampling: bool) -> Self {
+        AmbientOcclusion {
+            distance,
+            cosine_sampling,
+        }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn color(&self, r: &Ray, scene: &Scene, _stats: &mut PathStats) -> Vec3 {
+        let world = scene.objects();
+        match world.hit(r, 0.001, f32::MAX) {
+            Some(rec) => {
+                // Face the normal towards the viewer so closed and open surfaces agree.
+                let normal = if rec.normal.dot(r.direction) > 0.0 {
+                    -rec.normal
+                } else {
+                    rec.normal
+                };
+                let (local, pdf) = if self.cosine_sampling {
+                    let local = cosine_hemisphere();
+                    (local, cosine_hemisphere_pdf(local.z()))
+                } else {
+                    (uniform_hemisphere(), uniform_hemisphere_pdf())
+                };
+                let direction = Onb::new(normal).local_to_world(&local);
+                let probe = Ray::new(rec.p, direction, r.time);
+                if pdf <= 0.0 || world.hit(&probe, 0.001, self.distance).is_some() {
+                    return Vec3::default();
+                }
+                let visibility = local.z() / PI / pdf;
+                Vec3::new(visibility, visibility, visibility)
+            }
+            None => Vec3::default(),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use crate::hittables::Hitable;
+use crate::integrators::{Integrator, PathStats};
+use crate::math::Vec3;
+use crate::physics::Ray;
+use crate::scene::Scene;
+
+/// What `DebugIntegrator` shows for the first hit along each ray.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DebugMode {
+    /// Shading normal mapped from [-1, 1] to [0, 1].
+    Normal,
+    /// Texture coordinates in the red and green channels.
+    Uv,
+    /// Attenuation of the material, black when it absorbs the ray.
+    Albedo,
+}
+
+/// Visualises surface attributes instead of light transport.
+pub struct DebugIntegrator {
+    mode: DebugMode,
+}
+
+impl DebugIntegrator {
+    pub fn new(mode: DebugMode) -> Self {
+        DebugIntegrator { mode }
+    }
+}
+
+impl Integrator for DebugIntegrator {
+    fn color(&self, r: &Ray, scene: &Scene, _stats: &mut PathStats) -> Vec3 {
+        let rec = match scene.objects().hit(r, 0.001, f32::MAX) {
+            Some(rec) => rec,
+            None => return Vec3::default(),
+        };
+        match self.mode {
+            DebugMode::Normal => 0.5 * (rec.normal + Vec3::new(1.0, 1.0, 1.0)),
+            DebugMode::Uv => Vec3::new(rec.u, rec.v, 0.0),
+            DebugMode::Albedo => match rec.material.scatter(r, &rec) {
+                Some(scatter) => scatter.attenuation,
+                None => Vec3::default(),
+            },
+        }
+    }
+}
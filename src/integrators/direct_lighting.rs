@@ -0,0 +1,176 @@
+use crate::hittables::{HitRecord, Hitable};
+use crate::integrators::{emission_weight, Integrator, MisHeuristic, PathStats};
+use crate::lights::EnvironmentLight;
+use crate::math::Vec3;
+use crate::physics::{Ray, ScatterRecord};
+use crate::scene::Scene;
+
+// Relative distance short of the sampled point at which a shadow ray counts as blocked.
+const SHADOW_EPSILON: f32 = 1e-3;
+
+// Light arriving at `rec` from one point sampled on the scene lights, one direction of the
+// environment and every delta light, through shadow rays. `scatter` is the result of
+// scattering `r` at `rec`, `heuristic` weighs the area light and environment samples against
+// the BSDF sampling the same direction.
+pub(crate) fn sample_lights(
+    r: &Ray,
+    rec: &HitRecord,
+    scatter: &ScatterRecord,
+    scene: &Scene,
+    heuristic: Option<MisHeuristic>,
+) -> Vec3 {
+    let mut color = Vec3::default();
+    if !scene.lights().entities.is_empty() {
+        color += sample_area_lights(r, rec, scatter, scene, heuristic);
+    }
+    if let Some(environment) = scene.environment() {
+        color += sample_environment(r, rec, scatter, scene, heuristic, environment);
+    }
+    let wo = -r.direction;
+    for light in scene.delta_lights() {
+        let sample = match light.sample(&rec.p) {
+            Some(sample) => sample,
+            None => continue,
+        };
+        let f = scatter.eval(&sample.direction, &wo);
+        if f.squared_length() <= 0.0 {
+            continue;
+        }
+        // BSDF sampling never finds a delta light, so the sample needs no weighting.
+        let shadow = Ray::new(rec.p, sample.direction, r.time);
+        let t_max = sample.distance * (1.0 - SHADOW_EPSILON);
+        if scene.objects().hit(&shadow, 0.001, t_max).is_none() {
+            color += f * sample.radiance;
+        }
+    }
+    color
+}
+
+fn sample_area_lights(
+    r: &Ray,
+    rec: &HitRecord,
+    scatter: &ScatterRecord,
+    scene: &Scene,
+    heuristic: Option<MisHeuristic>,
+) -> Vec3 {
+    let sample = match scene.lights().sample(&rec.p) {
+        Some(sample) if sample.pdf > 0.0 => sample,
+        _ => return Vec3::default(),
+    };
+    let wi = sample.p - rec.p;
+    let wo = -r.direction;
+    let f = scatter.eval(&wi, &wo);
+    if f.squared_length() <= 0.0 {
+        return Vec3::default();
+    }
+    let weight = heuristic.map_or(1.0, |h| {
+        h.weight(sample.pdf, rec.material.pdf(rec, &wi, &wo))
+    });
+    let shadow = Ray::new(rec.p, wi, r.time);
+    match scene.objects().hit(&shadow, 0.001, f32::MAX) {
+        Some(light) if light.t > 1.0 - SHADOW_EPSILON => {
+            f * light.material.emitted(&shadow, &light) / sample.pdf * weight
+        }
+        _ => Vec3::default(),
+    }
+}
+
+fn sample_environment(
+    r: &Ray,
+    rec: &HitRecord,
+    scatter: &ScatterRecord,
+    scene: &Scene,
+    heuristic: Option<MisHeuristic>,
+    environment: &EnvironmentLight,
+) -> Vec3 {
+    let (wi, pdf) = match environment.sample() {
+        Some(sample) => sample,
+        None => return Vec3::default(),
+    };
+    let wo = -r.direction;
+    let f = scatter.eval(&wi, &wo);
+    if f.squared_length() <= 0.0 {
+        return Vec3::default();
+    }
+    let shadow = Ray::new(rec.p, wi, r.time);
+    if scene.objects().hit(&shadow, 0.001, f32::MAX).is_some() {
+        return Vec3::default();
+    }
+    let weight = heuristic.map_or(1.0, |h| h.weight(pdf, rec.material.pdf(rec, &wi, &wo)));
+    f * environment.radiance(&wi) / pdf * weight
+}
+
+// Environment light reaching the end of `r`, which left the scene. `bsdf_pdf` is the density
+// `r` was scattered with, `None` if light sampling couldn't have found it.
+pub(crate) fn escaped(
+    r: &Ray,
+    scene: &Scene,
+    bsdf_pdf: Option<f32>,
+    heuristic: Option<MisHeuristic>,
+) -> Vec3 {
+    let environment = match scene.environment() {
+        Some(environment) => environment,
+        None => return Vec3::default(),
+    };
+    let radiance = environment.radiance(&r.direction);
+    match bsdf_pdf {
+        Some(pdf) => radiance * emission_weight(heuristic, pdf, environment.pdf(&r.direction)),
+        None => radiance,
+    }
+}
+
+/// Emission seen directly plus the light reaching the first hit in one scattering event.
+///
+/// Diffuse and glossy surfaces also sample the scene lights, combined with the scattered
+/// ray by `heuristic`. Without one, lights that can be sampled are only found that way.
+pub struct DirectLighting {
+    heuristic: Option<MisHeuristic>,
+}
+
+impl DirectLighting {
+    pub fn new(heuristic: Option<MisHeuristic>) -> Self {
+        DirectLighting { heuristic }
+    }
+}
+
+impl Integrator for DirectLighting {
+    fn color(&self, r: &Ray, scene: &Scene, _stats: &mut PathStats) -> Vec3 {
+        let world = scene.objects();
+        match world.hit(r, 0.001, f32::MAX) {
+            Some(rec) => {
+                let mut color = rec.material.emitted(r, &rec);
+                let scatter = match rec.material.scatter(r, &rec) {
+                    Some(scatter) => scatter,
+                    None => return color,
+                };
+                if !scatter.specular && scene.has_lights() {
+                    color += sample_lights(r, &rec, &scatter, scene, self.heuristic);
+                }
+                let scattered = scatter.ray(r.time);
+                let bsdf_pdf = if scatter.specular {
+                    None
+                } else {
+                    Some(scatter.pdf)
+                };
+                match world.hit(&scattered, 0.001, f32::MAX) {
+                    Some(light) => {
+                        let weight = bsdf_pdf.map_or(1.0, |pdf| {
+                            let light_pdf =
+                                scene.lights().pdf(&scattered.origin, &scattered.direction);
+                            emission_weight(self.heuristic, pdf, light_pdf)
+                        });
+                        color += scatter.attenuation
+                            * light.material.emitted(&scattered, &light)
+                            * weight;
+                    }
+                    None => {
+                        color += scatter.attenuation
+                            * escaped(&scattered, scene, bsdf_pdf, self.heuristic)
+                    }
+                }
+                color
+            }
+            None => escaped(r, scene, None, self.heuristic),
+        }
+    }
+}
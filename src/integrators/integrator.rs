@@ -0,0 +1,46 @@
+use crate::math::Vec3;
+use crate::physics::Ray;
+use crate::scene::Scene;
+use std::fmt;
+
+/// Counts gathered by an integrator over all the rays it traced.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PathStats {
+    pub paths: u64,
+    /// Scattering events over all paths.
+    pub bounces: u64,
+}
+
+impl PathStats {
+    pub fn add(&mut self, other: &PathStats) {
+        self.paths += other.paths;
+        self.bounces += other.bounces;
+    }
+
+    pub fn average_length(&self) -> f64 {
+        if self.paths == 0 {
+            0.0
+        } else {
+            self.bounces as f64 / self.paths as f64
+        }
+    }
+}
+
+impl fmt::Display for PathStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} paths, {:.2} bounces on average",
+            self.paths,
+            self.average_length()
+        )
+    }
+}
+
+/// Light transport algorithm, estimates the radiance arriving along a camera ray.
+///
+/// `stats` belongs to the caller's thread, so integrators that count their paths can do so
+/// without synchronising; the others leave it alone.
+pub trait Integrator: Sync {
+    fn color(&self, r: &Ray, scene: &Scene, stats: &mut PathStats) -> Vec3;
+}
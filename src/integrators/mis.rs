@@ -0,0 +1,89 @@
+/// Weights combining light sampling with BSDF sampling of the same light.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MisHeuristic {
+    Balance,
+    Power,
+}
+
+impl MisHeuristic {
+    // Weight of a sample taken with density `pdf` when `other` could have produced it too.
+    pub fn weight(self, pdf: f32, other: f32) -> f32 {
+        let (a, b) = match self {
+            MisHeuristic::Balance => (pdf, other),
+            MisHeuristic::Power => (pdf * pdf, other * other),
+        };
+        if a + b > 0.0 {
+            a / (a + b)
+        } else {
+            0.0
+        }
+    }
+}
+
+// Weight of emission found by a BSDF sampled ray with density `bsdf_pdf` that the lights
+// could have sampled with `light_pdf`. Without a heuristic light sampling gets it all.
+pub(crate) fn emission_weight(
+    heuristic: Option<MisHeuristic>,
+    bsdf_pdf: f32,
+    light_pdf: f32,
+) -> f32 {
+    match heuristic {
+        _ if light_pdf <= 0.0 => 1.0,
+        Some(heuristic) => heuristic.weight(bsdf_pdf, light_pdf),
+        None => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PDFS: [f32; 6] = [0.0, 1e-4, 0.2, 1.0, 3.5, 1e4];
+
+    #[test]
+    fn weights_of_both_strategies_sum_to_one() {
+        for heuristic in [MisHeuristic::Balance, MisHeuristic::Power] {
+            for p in PDFS {
+                for q in PDFS {
+                    if p + q == 0.0 {
+                        continue;
+                    }
+                    let sum = heuristic.weight(p, q) + heuristic.weight(q, p);
+                    assert!(
+                        (sum - 1.0).abs() < 1e-5,
+                        "{:?} weights for {} and {} sum to {}",
+                        heuristic,
+                        p,
+                        q,
+                        sum
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weights_follow_their_heuristic() {
+        assert_eq!(MisHeuristic::Balance.weight(1.0, 3.0), 0.25);
+        assert_eq!(MisHeuristic::Power.weight(1.0, 3.0), 0.1);
+        assert_eq!(MisHeuristic::Power.weight(2.0, 2.0), 0.5);
+        assert_eq!(MisHeuristic::Balance.weight(2.0, 0.0), 1.0);
+        assert_eq!(MisHeuristic::Power.weight(0.0, 2.0), 0.0);
+        assert_eq!(MisHeuristic::Balance.weight(0.0, 0.0), 0.0);
+        assert_eq!(MisHeuristic::Power.weight(0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn emission_weight_leaves_unsampled_lights_to_the_bsdf() {
+        for heuristic in [None, Some(MisHeuristic::Balance), Some(MisHeuristic::Power)] {
+            assert_eq!(emission_weight(heuristic, 0.5, 0.0), 1.0);
+            assert_eq!(emission_weight(heuristic, 0.0, 0.0), 1.0);
+        }
+        assert_eq!(emission_weight(None, 0.5, 2.0), 0.0);
+        assert_eq!(
+            emission_weight(Some(MisHeuristic::Power), 1.0, 3.0),
+            MisHeuristic::Power.weight(1.0, 3.0)
+        );
+        assert_eq!(emission_weight(Some(MisHeuristic::Balance), 0.0, 3.0), 0.0);
+    }
+}
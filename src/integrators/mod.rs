@@ -0,0 +1,10 @@
+mod ambient_occlusion;
+mod debug;
+mod direct_lighting;
+mod integrator;
+mod mis;
+mod path_tracer;
+
+pub use self::{
+    ambient_occlusion::*, debug::*, direct_lighting::*, integrator::*, mis::*, path_tracer::*,
+};
@@ -0,0 +1,93 @@
+use crate::hittables::Hitable;
+use crate::integrators::{
+    emission_weight, escaped, sample_lights, Integrator, MisHeuristic, PathStats,
+};
+use crate::math::Vec3;
+use crate::physics::Ray;
+use crate::rand::prelude::*;
+use crate::scene::Scene;
+
+/// Path tracing that follows the material scattering in a loop, carrying the throughput
+/// of the path so far. Paths end after the scene's `max_depth` bounces, or earlier by
+/// Russian roulette once they are past its `roulette_depth`.
+///
+/// Diffuse and glossy bounces also sample the scene lights, and `heuristic` weighs those
+/// samples against the scattered ray finding the same light. Without a heuristic the
+/// lights that can be sampled are only found that way.
+pub struct PathTracer {
+    heuristic: Option<MisHeuristic>,
+}
+
+impl PathTracer {
+    pub fn new(heuristic: Option<MisHeuristic>) -> Self {
+        PathTracer { heuristic }
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        PathTracer::new(Some(MisHeuristic::Power))
+    }
+}
+
+impl Integrator for PathTracer {
+    fn color(&self, r: &Ray, scene: &Scene, stats: &mut PathStats) -> Vec3 {
+        let settings = scene.settings();
+        let mut ray = Ray::new(r.origin, r.direction, r.time);
+        let mut radiance = Vec3::default();
+        let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+        // Density `ray` was scattered with, `None` for the camera ray and after specular
+        // bounces that light sampling can't reproduce.
+        let mut bsdf_pdf: Option<f32> = None;
+        let mut depth = 0;
+        loop {
+            let rec = match scene.objects().hit(&ray, 0.001, f32::MAX) {
+                Some(rec) => rec,
+                None => {
+                    radiance += throughput * escaped(&ray, scene, bsdf_pdf, self.heuristic);
+                    break;
+                }
+            };
+            let mut emitted = rec.material.emitted(&ray, &rec);
+            if let Some(pdf) = bsdf_pdf {
+                if emitted.squared_length() > 0.0 {
+                    let light_pdf = scene.lights().pdf(&ray.origin, &ray.direction);
+                    emitted *= emission_weight(self.heuristic, pdf, light_pdf);
+                }
+            }
+            radiance += throughput * emitted;
+            if depth >= settings.max_depth {
+                break;
+            }
+            let scatter = match rec.material.scatter(&ray, &rec) {
+                Some(scatter) => scatter,
+                None => break,
+            };
+            if scatter.specular {
+                bsdf_pdf = None;
+            } else {
+                if scene.has_lights() {
+                    radiance +=
+                        throughput * sample_lights(&ray, &rec, &scatter, scene, self.heuristic);
+                }
+                bsdf_pdf = Some(scatter.pdf);
+            }
+            let mut attenuation = scatter.attenuation;
+            if depth >= settings.roulette_depth {
+                // Paths survive as likely as they are bright, and are boosted to make up for
+                // the ones that don't.
+                let survival = (throughput * attenuation).max_component().min(1.0);
+                if survival <= 0.0 || rand::thread_rng().gen::<f32>() >= survival {
+                    break;
+                }
+                attenuation /= survival;
+            }
+            throughput *= attenuation;
+            ray = scatter.ray(ray.time);
+            depth += 1;
+        }
+        stats.paths += 1;
+        stats.bounces += depth as u64;
+        radiance
+    }
+}
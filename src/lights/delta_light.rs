@@ -0,0 +1,17 @@
+use crate::math::Vec3;
+
+/// Light arriving at a point from one sampled direction of a `DeltaLight`.
+pub struct DeltaSample {
+    /// Unit direction towards the light.
+    pub direction: Vec3,
+    /// Distance a shadow ray has to cover, `f32::MAX` for lights at infinity.
+    pub distance: f32,
+    /// Incident radiance divided by the density of `direction`.
+    pub radiance: Vec3,
+}
+
+/// A light without a surface. Rays never hit one, so it only contributes through light
+/// sampling and does not show up in mirrors or glass.
+pub trait DeltaLight: Sync + Send {
+    fn sample(&self, origin: &Vec3) -> Option<DeltaSample>;
+}
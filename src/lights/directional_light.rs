@@ -0,0 +1,39 @@
+use crate::lights::{DeltaLight, DeltaSample};
+use crate::math::sampling::uniform_cone;
+use crate::math::{Onb, Vec3};
+
+/// Light from infinitely far away travelling along `direction`, like the sun. `irradiance`
+/// arrives on a surface facing it. With an angular diameter, in degrees, the light comes from
+/// a disc of that size in the sky and casts soft shadows.
+pub struct DirectionalLight {
+    frame: Onb,
+    irradiance: Vec3,
+    cos_radius: f32,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, irradiance: Vec3, angular_diameter: f32) -> Self {
+        DirectionalLight {
+            frame: Onb::new(-direction.unit_vector()),
+            irradiance,
+            cos_radius: (angular_diameter / 2.0).to_radians().cos(),
+        }
+    }
+}
+
+impl DeltaLight for DirectionalLight {
+    // Directions are uniform over the disc, which carries the irradiance evenly, so the
+    // radiance over the density is the irradiance itself.
+    fn sample(&self, _origin: &Vec3) -> Option<DeltaSample> {
+        let direction = if self.cos_radius < 1.0 {
+            self.frame.local_to_world(&uniform_cone(self.cos_radius))
+        } else {
+            self.frame.w
+        };
+        Some(DeltaSample {
+            direction,
+            distance: f32::MAX,
+            radiance: self.irradiance,
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use crate::math::{Distribution2D, Vec3};
+use image::codecs::hdr::HdrDecoder;
+use image::ImageResult;
+use rand::prelude::*;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Light from infinitely far away in every direction rays escape the scene in, read from an
+/// equirectangular image whose top row is straight up. `rotation` turns it around the y axis
+/// by that many degrees.
+///
+/// Directions are sampled in proportion to the brightness of the pixels, so a small sun in a
+/// large sky gets most of the samples instead of being found by chance.
+pub struct EnvironmentLight {
+    pixels: Vec<Vec3>,
+    width: usize,
+    height: usize,
+    sin_rotation: f32,
+    cos_rotation: f32,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentLight {
+    pub fn new(pixels: Vec<Vec3>, width: usize, height: usize, rotation: f32) -> Self {
+        // Rows near the poles cover less solid angle than their pixel count suggests.
+        let weights: Vec<f32> = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, pixel)| {
+                let theta = ((i / width) as f32 + 0.5) / height as f32 * PI;
+                luminance(pixel) * theta.sin()
+            })
+            .collect();
+        EnvironmentLight {
+            distribution: Distribution2D::new(&weights, width, height),
+            pixels,
+            width,
+            height,
+            sin_rotation: rotation.to_radians().sin(),
+            cos_rotation: rotation.to_radians().cos(),
+        }
+    }
+
+    /// The same radiance from everywhere.
+    pub fn constant(radiance: Vec3) -> Self {
+        EnvironmentLight::new(vec![radiance], 1, 1, 0.0)
+    }
+
+    /// Reads a Radiance `.hdr` image, scaled by `intensity`.
+    pub fn open<P: AsRef<Path>>(path: P, intensity: f32, rotation: f32) -> ImageResult<Self> {
+        let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+        let metadata = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()?
+            .iter()
+            .map(|pixel| Vec3::new(pixel[0], pixel[1], pixel[2]) * intensity)
+            .collect();
+        Ok(EnvironmentLight::new(
+            pixels,
+            metadata.width as usize,
+            metadata.height as usize,
+            rotation,
+        ))
+    }
+
+    /// Radiance arriving from `direction`.
+    pub fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let (u, v) = self.uv(direction);
+        let i = ((u * self.width as f32) as usize).min(self.width - 1);
+        let j = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.pixels[i + self.width * j]
+    }
+
+    /// A direction towards the environment and its density per unit solid angle.
+    pub fn sample(&self) -> Option<(Vec3, f32)> {
+        let mut rng = rand::thread_rng();
+        let (u, v, pdf) = self.distribution.sample(rng.gen(), rng.gen());
+        let theta = v * PI;
+        let phi = u * 2.0 * PI;
+        let sin_theta = theta.sin();
+        if pdf <= 0.0 || sin_theta <= 0.0 {
+            return None;
+        }
+        let local = Vec3::new(sin_theta * phi.cos(), theta.cos(), sin_theta * phi.sin());
+        let direction = self.rotate(&local, self.sin_rotation);
+        Some((direction, pdf / (2.0 * PI * PI * sin_theta)))
+    }
+
+    /// Density with which `sample` picks `direction`.
+    pub fn pdf(&self, direction: &Vec3) -> f32 {
+        let (u, v) = self.uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn uv(&self, direction: &Vec3) -> (f32, f32) {
+        let d = self.rotate(direction, -self.sin_rotation).unit_vector();
+        let mut phi = d.z().atan2(d.x());
+        if phi < 0.0 {
+            phi += 2.0 * PI;
+        }
+        (phi / (2.0 * PI), d.y().clamp(-1.0, 1.0).acos() / PI)
+    }
+
+    // Around the y axis, by the rotation or its inverse depending on the sign of `sin`.
+    fn rotate(&self, d: &Vec3, sin: f32) -> Vec3 {
+        Vec3::new(
+            self.cos_rotation * d.x() - sin * d.z(),
+            d.y(),
+            sin * d.x() + self.cos_rotation * d.z(),
+        )
+    }
+}
+
+fn luminance(color: &Vec3) -> f32 {
+    0.2126 * color.r() + 0.7152 * color.g() + 0.0722 * color.b()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A dim sky with uneven rows and columns, turned so the rotation is exercised too.
+    fn sky() -> EnvironmentLight {
+        let (width, height) = (8, 4);
+        let pixels = (0..width * height)
+            .map(|i| {
+                let brightness = 1.0 + ((i * 7) % 5) as f32 * 0.5;
+                Vec3::new(brightness, brightness, brightness)
+            })
+            .collect();
+        EnvironmentLight::new(pixels, width, height, 30.0)
+    }
+
+    #[test]
+    fn sample_pdf_matches_pdf() {
+        let light = sky();
+        let mut checked = 0;
+        for _ in 0..20_000 {
+            let (direction, pdf) = light.sample().unwrap();
+            assert!((direction.length() - 1.0).abs() < 1e-4);
+            // Right at the poles `acos` cannot recover the angle closely enough in f32.
+            if direction.y().abs() > 0.999 {
+                continue;
+            }
+            assert!(
+                (light.pdf(&direction) - pdf).abs() <= 1e-3 * pdf,
+                "sampled {:?} with pdf {}, pdf gives {}",
+                direction,
+                pdf,
+                light.pdf(&direction)
+            );
+            checked += 1;
+        }
+        assert!(checked > 19_000);
+    }
+
+    #[test]
+    fn pdf_integrates_to_one_over_the_sphere() {
+        let light = sky();
+        let mut rng = StdRng::seed_from_u64(3);
+        let samples = 200_000;
+        let mut total = 0.0;
+        for _ in 0..samples {
+            let z = 1.0 - 2.0 * rng.gen::<f32>();
+            let r = (1.0 - z * z).max(0.0).sqrt();
+            let phi = 2.0 * PI * rng.gen::<f32>();
+            total += light.pdf(&Vec3::new(r * phi.cos(), r * phi.sin(), z));
+        }
+        let integral = 4.0 * PI * total / samples as f32;
+        assert!(
+            (integral - 1.0).abs() < 0.02,
+            "pdf integrates to {}",
+            integral
+        );
+    }
+}
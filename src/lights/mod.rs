@@ -0,0 +1,9 @@
+mod delta_light;
+mod directional_light;
+mod environment_light;
+mod point_light;
+mod spot_light;
+
+pub use self::{
+    delta_light::*, directional_light::*, environment_light::*, point_light::*, spot_light::*,
+};
@@ -0,0 +1,33 @@
+use crate::lights::{DeltaLight, DeltaSample};
+use crate::math::Vec3;
+
+/// Shines `intensity` equally in all directions from `position`.
+pub struct PointLight {
+    position: Vec3,
+    intensity: Vec3,
+}
+
+impl PointLight {
+    pub fn new(position: Vec3, intensity: Vec3) -> Self {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl DeltaLight for PointLight {
+    fn sample(&self, origin: &Vec3) -> Option<DeltaSample> {
+        let d = self.position - *origin;
+        let distance_squared = d.squared_length();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        Some(DeltaSample {
+            direction: d / distance,
+            distance,
+            radiance: self.intensity / distance_squared,
+        })
+    }
+}
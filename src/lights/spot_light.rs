@@ -0,0 +1,57 @@
+use crate::lights::{DeltaLight, DeltaSample};
+use crate::math::Vec3;
+
+/// A point light restricted to a cone around `direction`. The light fades out smoothly
+/// between the `falloff` and `angle` half angles, in degrees.
+pub struct SpotLight {
+    position: Vec3,
+    direction: Vec3,
+    intensity: Vec3,
+    cos_angle: f32,
+    cos_falloff: f32,
+}
+
+impl SpotLight {
+    pub fn new(position: Vec3, direction: Vec3, intensity: Vec3, angle: f32, falloff: f32) -> Self {
+        let falloff = falloff.min(angle);
+        SpotLight {
+            position,
+            direction: direction.unit_vector(),
+            intensity,
+            cos_angle: angle.to_radians().cos(),
+            cos_falloff: falloff.to_radians().cos(),
+        }
+    }
+
+    fn falloff(&self, cos: f32) -> f32 {
+        if cos >= self.cos_falloff {
+            return 1.0;
+        }
+        if cos <= self.cos_angle {
+            return 0.0;
+        }
+        let t = (cos - self.cos_angle) / (self.cos_falloff - self.cos_angle);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl DeltaLight for SpotLight {
+    fn sample(&self, origin: &Vec3) -> Option<DeltaSample> {
+        let d = self.position - *origin;
+        let distance_squared = d.squared_length();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = d / distance;
+        let falloff = self.falloff(-direction.dot(self.direction));
+        if falloff <= 0.0 {
+            return None;
+        }
+        Some(DeltaSample {
+            direction,
+            distance,
+            radiance: self.intensity * (falloff / distance_squared),
+        })
+    }
+}
@@ -0,0 +1,33 @@
+use crate::physics::Material;
+use crate::textures::Texture;
+use std::sync::Arc;
+
+mod obj;
+mod ply;
+mod scene_file;
+
+pub use self::{obj::*, ply::*, scene_file::*};
+
+pub type SharedTexture = Arc<dyn Texture + Send>;
+pub type SharedMaterial = Arc<dyn Material + Send>;
+
+// Writes `contents` to a fresh file for a loader test, `name` has to be unique among the tests.
+#[cfg(test)]
+pub(crate) fn test_file(name: &str, contents: &[u8]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust-tracer-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+// Plain grey material for meshes loaded in tests.
+#[cfg(test)]
+pub(crate) fn test_material() -> SharedMaterial {
+    use crate::materials::Lambertian;
+    use crate::math::Vec3;
+    use crate::textures::ConstantTexture;
+    Arc::new(Lambertian::new(ConstantTexture::new(Vec3::new(
+        0.5, 0.5, 0.5,
+    ))))
+}
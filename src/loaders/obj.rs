@@ -0,0 +1,651 @@
+use crate::hittables::{Cutout, HittableList, TriangleMesh};
+use crate::loaders::{SceneError, SharedMaterial, SharedTexture};
+use crate::materials::{Dielectric, DiffuseLight, Lambertian, Metal, Principled};
+use crate::math::Vec3;
+use crate::textures::{ConstantTexture, ImageTexture};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+
+fn error(file: &Path, line: usize, key: &str, message: String) -> SceneError {
+    SceneError::Parse {
+        file: file.to_path_buf(),
+        line,
+        key: Some(key.to_string()),
+        message,
+    }
+}
+
+fn read(file: &Path) -> Result<String, SceneError> {
+    fs::read_to_string(file).map_err(|source| SceneError::Io {
+        file: file.to_path_buf(),
+        source,
+    })
+}
+
+fn floats<const N: usize>(
+    file: &Path,
+    line: usize,
+    key: &str,
+    tokens: SplitWhitespace,
+) -> Result<[f32; N], SceneError> {
+    leading_floats::<N>(file, line, key, tokens, N)
+}
+
+// Up to `N` numbers of which the first `required` must be given, the rest default to zero.
+fn leading_floats<const N: usize>(
+    file: &Path,
+    line: usize,
+    key: &str,
+    tokens: SplitWhitespace,
+    required: usize,
+) -> Result<[f32; N], SceneError> {
+    let mut values = [0.0; N];
+    let mut count = 0;
+    for token in tokens {
+        if count == N {
+            break;
+        }
+        values[count] = token.parse().map_err(|_| {
+            error(
+                file,
+                line,
+                key,
+                format!("expected a number, got `{}`", token),
+            )
+        })?;
+        count += 1;
+    }
+    if count < required {
+        return Err(error(
+            file,
+            line,
+            key,
+            format!("expected {} numbers", required),
+        ));
+    }
+    Ok(values)
+}
+
+// OBJ indices are 1-based, negative ones count back from the last element read so far.
+fn resolve_index(raw: &str, len: usize) -> Option<usize> {
+    let index: i64 = raw.parse().ok()?;
+    let index = match index {
+        i if i > 0 => i - 1,
+        i if i < 0 => len as i64 + i,
+        _ => return None,
+    };
+    if index >= 0 && (index as usize) < len {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+#[derive(Default)]
+struct MtlMaterial {
+    kd: Option<Vec3>,
+    ks: Option<Vec3>,
+    ke: Option<Vec3>,
+    ni: Option<f32>,
+    ns: Option<f32>,
+    d: Option<f32>,
+    illum: Option<u32>,
+    map_kd: Option<PathBuf>,
+    map_d: Option<PathBuf>,
+    // Physically based extension.
+    pr: Option<f32>,
+    pm: Option<f32>,
+    ps: Option<f32>,
+    pc: Option<f32>,
+    map_pr: Option<PathBuf>,
+    map_pm: Option<PathBuf>,
+}
+
+fn image_texture(
+    textures: &mut HashMap<PathBuf, SharedTexture>,
+    path: &Path,
+    file: &Path,
+    line: usize,
+    key: &str,
+) -> Result<SharedTexture, SceneError> {
+    if let Some(texture) = textures.get(path) {
+        return Ok(texture.clone());
+    }
+    let texture: SharedTexture =
+        Arc::new(ImageTexture::open(path).map_err(|e| error(file, line, key, e.to_string()))?);
+    textures.insert(path.to_path_buf(), texture.clone());
+    Ok(texture)
+}
+
+fn constant(value: f32) -> SharedTexture {
+    Arc::new(ConstantTexture::new(Vec3::new(value, value, value)))
+}
+
+impl MtlMaterial {
+    fn build(
+        &self,
+        textures: &mut HashMap<PathBuf, SharedTexture>,
+        file: &Path,
+        line: usize,
+    ) -> Result<SharedMaterial, SceneError> {
+        let black = Vec3::default();
+        let ke = self.ke.unwrap_or(black);
+        let illum = self.illum.unwrap_or(2);
+        if self.pr.is_some()
+            || self.pm.is_some()
+            || self.ps.is_some()
+            || self.pc.is_some()
+            || self.map_pr.is_some()
+            || self.map_pm.is_some()
+        {
+            let roughness = match &self.map_pr {
+                Some(path) => image_texture(textures, path, file, line, "map_Pr")?,
+                None => constant(self.pr.unwrap_or(0.5)),
+            };
+            let metallic = match &self.map_pm {
+                Some(path) => image_texture(textures, path, file, line, "map_Pm")?,
+                None => constant(self.pm.unwrap_or(0.0)),
+            };
+            return Ok(Arc::new(
+                Principled::new(self.albedo(textures, file, line)?)
+                    .roughness(roughness)
+                    .metallic(metallic)
+                    .sheen(constant(self.ps.unwrap_or(0.0)))
+                    .clearcoat(constant(self.pc.unwrap_or(0.0)))
+                    .transmission(constant(1.0 - self.d.unwrap_or(1.0)))
+                    .emission(Arc::new(ConstantTexture::new(ke))),
+            ));
+        }
+        if ke.squared_length() > 0.0 {
+            return Ok(Arc::new(DiffuseLight::new(ConstantTexture::new(ke))));
+        }
+        if matches!(self.d, Some(d) if d < 1.0) || [4, 6, 7, 9].contains(&illum) {
+            return Ok(Arc::new(Dielectric::new(self.ni.unwrap_or(1.5))));
+        }
+        if [3, 5, 8].contains(&illum) {
+            // Blinn-Phong exponent to an approximate roughness.
+            let fuzz = (2.0 / (self.ns.unwrap_or(0.0) + 2.0)).sqrt();
+            return Ok(Arc::new(Metal::new(
+                self.ks.unwrap_or(self.kd.unwrap_or(black)),
+                fuzz,
+            )));
+        }
+        Ok(Arc::new(Lambertian::new(
+            self.albedo(textures, file, line)?,
+        )))
+    }
+
+    fn albedo(
+        &self,
+        textures: &mut HashMap<PathBuf, SharedTexture>,
+        file: &Path,
+        line: usize,
+    ) -> Result<SharedTexture, SceneError> {
+        match &self.map_kd {
+            Some(path) => image_texture(textures, path, file, line, "map_Kd"),
+            None => Ok(Arc::new(ConstantTexture::new(
+                self.kd.unwrap_or_else(|| Vec3::new(0.8, 0.8, 0.8)),
+            ))),
+        }
+    }
+}
+
+// Reads the materials of an MTL file, and the `map_d` masks of those that have one.
+fn load_mtl(
+    file: &Path,
+    materials: &mut HashMap<String, SharedMaterial>,
+    masks: &mut HashMap<String, SharedTexture>,
+    textures: &mut HashMap<PathBuf, SharedTexture>,
+) -> Result<(), SceneError> {
+    let source = read(file)?;
+    let dir = file.parent().unwrap_or_else(|| Path::new(""));
+    let mut current: Option<(String, usize, MtlMaterial)> = None;
+    let mut finish = |current: Option<(String, usize, MtlMaterial)>| -> Result<(), SceneError> {
+        if let Some((name, line, mtl)) = current {
+            let material = mtl.build(textures, file, line)?;
+            if let Some(path) = &mtl.map_d {
+                let mask = ImageTexture::open_alpha(path)
+                    .map_err(|e| error(file, line, "map_d", e.to_string()))?;
+                masks.insert(name.clone(), Arc::new(mask));
+            }
+            materials.insert(name, material);
+        }
+        Ok(())
+    };
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut tokens = text.split_whitespace();
+        let key = match tokens.next() {
+            Some(key) if !key.starts_with('#') => key,
+            _ => continue,
+        };
+        if key == "newmtl" {
+            let name = tokens
+                .next()
+                .ok_or_else(|| error(file, line, key, String::from("missing material name")))?;
+            finish(current.take())?;
+            current = Some((name.to_string(), line, MtlMaterial::default()));
+            continue;
+        }
+        let mtl = match current.as_mut() {
+            Some((_, _, mtl)) => mtl,
+            None => {
+                return Err(error(
+                    file,
+                    line,
+                    key,
+                    String::from("expected `newmtl` first"),
+                ))
+            }
+        };
+        let color =
+            |tokens| floats::<3>(file, line, key, tokens).map(|c| Vec3::new(c[0], c[1], c[2]));
+        let scalar = |tokens| floats::<1>(file, line, key, tokens).map(|v| v[0]);
+        match key {
+            "Kd" => mtl.kd = Some(color(tokens)?),
+            "Ks" => mtl.ks = Some(color(tokens)?),
+            "Ke" => mtl.ke = Some(color(tokens)?),
+            "Ni" => mtl.ni = Some(scalar(tokens)?),
+            "Ns" => mtl.ns = Some(scalar(tokens)?),
+            "d" => mtl.d = Some(scalar(tokens)?),
+            "Tr" => mtl.d = Some(1.0 - scalar(tokens)?),
+            "illum" => mtl.illum = Some(scalar(tokens)? as u32),
+            "Pr" => mtl.pr = Some(scalar(tokens)?),
+            "Pm" => mtl.pm = Some(scalar(tokens)?),
+            "Ps" => mtl.ps = Some(scalar(tokens)?),
+            "Pc" => mtl.pc = Some(scalar(tokens)?),
+            // Texture options may precede the file name, which always comes last.
+            "map_Kd" | "map_d" | "map_Pr" | "map_Pm" => {
+                let path = match tokens.last() {
+                    Some(name) => dir.join(name),
+                    None => return Err(error(file, line, key, String::from("missing file name"))),
+                };
+                match key {
+                    "map_Kd" => mtl.map_kd = Some(path),
+                    "map_d" => mtl.map_d = Some(path),
+                    "map_Pr" => mtl.map_pr = Some(path),
+                    _ => mtl.map_pm = Some(path),
+                }
+            }
+            _ => {}
+        }
+    }
+    finish(current)
+}
+
+struct MeshBuilder {
+    material: Option<String>,
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f32, f32)>,
+    all_normals: bool,
+    all_uvs: bool,
+    indices: Vec<[u32; 3]>,
+    vertices: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+}
+
+impl MeshBuilder {
+    fn new(material: Option<String>) -> Self {
+        MeshBuilder {
+            material,
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            all_normals: true,
+            all_uvs: true,
+            indices: Vec::new(),
+            vertices: HashMap::new(),
+        }
+    }
+
+    fn vertex(
+        &mut self,
+        corner: (usize, Option<usize>, Option<usize>),
+        positions: &[Vec3],
+        uvs: &[(f32, f32)],
+        normals: &[Vec3],
+    ) -> u32 {
+        if let Some(&index) = self.vertices.get(&corner) {
+            return index;
+        }
+        let (v, vt, vn) = corner;
+        let index = self.positions.len() as u32;
+        self.positions.push(positions[v]);
+        match vt {
+            Some(vt) => self.uvs.push(uvs[vt]),
+            None => {
+                self.all_uvs = false;
+                self.uvs.push((0.0, 0.0));
+            }
+        }
+        match vn {
+            Some(vn) => self.normals.push(normals[vn]),
+            None => {
+                self.all_normals = false;
+                self.normals.push(Vec3::default());
+            }
+        }
+        self.vertices.insert(corner, index);
+        index
+    }
+}
+
+/// Loads a Wavefront OBJ file into one `TriangleMesh` per group and material.
+///
+/// Polygons are triangulated as fans. Materials from `mtllib` files are mapped onto the
+/// existing ones: any of the physically based `Pr`, `Pm`, `Ps`, `Pc`, `map_Pr` or `map_Pm`
+/// makes a `Principled` material, emissive `Ke` becomes `DiffuseLight`, transparent (`d` < 1
+/// or `illum` 4, 6, 7, 9) becomes `Dielectric` with `Ni`, mirror `illum` 3, 5, 8 becomes
+/// `Metal` with `Ks` and a fuzz derived from `Ns`, anything else is `Lambertian` with
+/// `map_Kd` or `Kd`. When `material` is given it replaces all of them. Meshes whose material
+/// has a `map_d` are cut out where the mask is below one half.
+pub fn load_obj<P: AsRef<Path>>(
+    path: P,
+    material: Option<SharedMaterial>,
+) -> Result<HittableList, SceneError> {
+    let file = path.as_ref();
+    let source = read(file)?;
+    let dir = file.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut uvs: Vec<(f32, f32)> = Vec::new();
+    let mut materials: HashMap<String, SharedMaterial> = HashMap::new();
+    let mut masks: HashMap<String, SharedTexture> = HashMap::new();
+    let mut textures: HashMap<PathBuf, SharedTexture> = HashMap::new();
+    let mut meshes: Vec<MeshBuilder> = Vec::new();
+    let mut lookup: HashMap<(String, Option<String>), usize> = HashMap::new();
+    let mut group = String::new();
+    let mut current_material: Option<String> = None;
+    let mut current: Option<usize> = None;
+
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut tokens = text.split_whitespace();
+        let key = match tokens.next() {
+            Some(key) if !key.starts_with('#') => key,
+            _ => continue,
+        };
+        match key {
+            "v" => {
+                let [x, y, z] = floats::<3>(file, line, key, tokens)?;
+                let position = Vec3::new(x, y, z);
+                if !position.is_finite() {
+                    return Err(error(
+                        file,
+                        line,
+                        key,
+                        String::from("vertex position is not finite"),
+                    ));
+                }
+                positions.push(position);
+            }
+            "vn" => {
+                let [x, y, z] = floats::<3>(file, line, key, tokens)?;
+                let normal = Vec3::new(x, y, z);
+                if !normal.is_finite() {
+                    return Err(error(
+                        file,
+                        line,
+                        key,
+                        String::from("vertex normal is not finite"),
+                    ));
+                }
+                // Zero normals stay zero, the mesh shades those with the face normal.
+                normals.push(if normal.squared_length() > 0.0 {
+                    normal.unit_vector()
+                } else {
+                    normal
+                });
+            }
+            // `v` is optional and defaults to zero, as is the `w` that we ignore.
+            "vt" => {
+                let [u, v] = leading_floats::<2>(file, line, key, tokens, 1)?;
+                uvs.push((u, v));
+            }
+            "g" | "o" => {
+                group = tokens.collect::<Vec<_>>().join(" ");
+                current = None;
+            }
+            "usemtl" => {
+                let name = tokens
+                    .next()
+                    .ok_or_else(|| error(file, line, key, String::from("missing material name")))?;
+                if material.is_none() && !materials.contains_key(name) {
+                    return Err(error(
+                        file,
+                        line,
+                        key,
+                        format!("unknown material `{}`", name),
+                    ));
+                }
+                current_material = Some(name.to_string());
+                current = None;
+            }
+            // Materials are not needed when the caller overrides them.
+            "mtllib" if material.is_none() => {
+                for name in tokens {
+                    load_mtl(&dir.join(name), &mut materials, &mut masks, &mut textures)?;
+                }
+            }
+            "f" => {
+                let mut corners = Vec::with_capacity(4);
+                for token in tokens {
+                    let mut parts = token.split('/');
+                    let invalid = || error(file, line, key, format!("invalid vertex `{}`", token));
+                    let v = parts
+                        .next()
+                        .and_then(|v| resolve_index(v, positions.len()))
+                        .ok_or_else(invalid)?;
+                    let vt = match parts.next() {
+                        Some("") | None => None,
+                        Some(vt) => Some(resolve_index(vt, uvs.len()).ok_or_else(invalid)?),
+                    };
+                    let vn = match parts.next() {
+                        Some("") | None => None,
+                        Some(vn) => Some(resolve_index(vn, normals.len()).ok_or_else(invalid)?),
+                    };
+                    corners.push((v, vt, vn));
+                }
+                if corners.len() < 3 {
+                    return Err(error(
+                        file,
+                        line,
+                        key,
+                        String::from("a face needs 3 vertices"),
+                    ));
+                }
+                let mesh_index = match current {
+                    Some(mesh_index) => mesh_index,
+                    None => {
+                        let lookup_key = (group.clone(), current_material.clone());
+                        let mesh_index = *lookup.entry(lookup_key).or_insert_with(|| {
+                            meshes.push(MeshBuilder::new(current_material.clone()));
+                            meshes.len() - 1
+                        });
+                        current = Some(mesh_index);
+                        mesh_index
+                    }
+                };
+                let mesh = &mut meshes[mesh_index];
+                let corners: Vec<u32> = corners
+                    .into_iter()
+                    .map(|corner| mesh.vertex(corner, &positions, &uvs, &normals))
+                    .collect();
+                for i in 1..corners.len() - 1 {
+                    mesh.indices.push([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if meshes.is_empty() {
+        return Err(SceneError::Parse {
+            file: file.to_path_buf(),
+            line: 0,
+            key: None,
+            message: String::from("no faces in file"),
+        });
+    }
+
+    let default_material: SharedMaterial = Arc::new(Lambertian::new(ConstantTexture::new(
+        Vec3::new(0.73, 0.73, 0.73),
+    )));
+    let mut list = HittableList::new(Vec::with_capacity(meshes.len()));
+    for mut mesh in meshes {
+        let mesh_material = match (&material, &mesh.material) {
+            (Some(material), _) => material.clone(),
+            (None, Some(name)) => materials[name].clone(),
+            (None, None) => default_material.clone(),
+        };
+        if !mesh.all_normals {
+            mesh.normals.clear();
+        }
+        if !mesh.all_uvs {
+            mesh.uvs.clear();
+        }
+        let mask = mesh.material.as_ref().and_then(|name| masks.get(name));
+        let triangles = TriangleMesh::new(
+            mesh.positions,
+            mesh.normals,
+            mesh.uvs,
+            mesh.indices,
+            mesh_material,
+        )
+        .map_err(|message| SceneError::Parse {
+            file: file.to_path_buf(),
+            line: 0,
+            key: None,
+            message,
+        })?;
+        match mask {
+            Some(mask) => list.push(Cutout::new(triangles, mask.clone(), 0.5)),
+            None => list.push(triangles),
+        };
+    }
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::Hitable;
+    use crate::loaders::{test_file, test_material};
+    use crate::physics::Ray;
+
+    const PENTAGON: &str = "v -1 0 -1
+v 1 0 -1
+v 1.5 0 0.5
+v 0 0 1.5
+v -1.5 0 0.5
+";
+
+    fn load(name: &str, source: &str) -> Result<HittableList, SceneError> {
+        load_obj(test_file(name, source.as_bytes()), Some(test_material()))
+    }
+
+    // Whether a ray straight down onto the y = 0 plane at `x`, `z` hits the mesh.
+    fn hits(mesh: &HittableList, x: f32, z: f32) -> bool {
+        let ray = Ray::new(Vec3::new(x, 1.0, z), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        mesh.hit(&ray, 0.001, f32::MAX).is_some()
+    }
+
+    #[test]
+    fn triangulates_polygons_as_fans() {
+        let mesh = load("fan.obj", &format!("{}f 1 2 3 4 5\n", PENTAGON)).unwrap();
+        // Centroids of the three fan triangles around the first vertex.
+        assert!(hits(&mesh, 0.5, -0.5));
+        assert!(hits(&mesh, 0.17, 0.33));
+        assert!(hits(&mesh, -0.83, 0.33));
+        assert!(!hits(&mesh, 0.0, 2.0));
+        assert!(!hits(&mesh, 1.4, -0.9));
+    }
+
+    #[test]
+    fn resolves_negative_indices_against_the_vertices_so_far() {
+        let source = format!(
+            "{}f -5 -4 -3 -2 -1\nv 10 0 0\nv 12 0 0\nv 10 0 2\nf -3/ -2 -1\n",
+            PENTAGON
+        );
+        let mesh = load("negative.obj", &source).unwrap();
+        assert!(hits(&mesh, 0.5, -0.5));
+        assert!(hits(&mesh, -0.83, 0.33));
+        assert!(hits(&mesh, 10.5, 0.5));
+        assert!(!hits(&mesh, 11.5, 1.5));
+    }
+
+    #[test]
+    fn rejects_indices_outside_the_vertices_so_far() {
+        for (name, face) in [
+            ("zero.obj", "f 0 1 2"),
+            ("ahead.obj", "f 1 2 6"),
+            ("behind.obj", "f -6 1 2"),
+        ] {
+            match load(name, &format!("{}{}\n", PENTAGON, face)) {
+                Err(SceneError::Parse { line, key, .. }) => {
+                    assert_eq!((line, key), (6, Some(String::from("f"))));
+                }
+                _ => panic!("`{}` should not load", face),
+            }
+        }
+    }
+
+    #[test]
+    fn drops_degenerate_faces_and_rejects_non_finite_vertices() {
+        let mesh = load(
+            "degenerate.obj",
+            &format!(
+                "{}f 1 1 2
+f 1 2 3
+",
+                PENTAGON
+            ),
+        )
+        .unwrap();
+        assert!(hits(&mesh, 0.8, -0.6));
+
+        match load(
+            "collapsed.obj",
+            &format!(
+                "{}f 1 1 2
+",
+                PENTAGON
+            ),
+        ) {
+            Err(SceneError::Parse { message, .. }) => {
+                assert_eq!(message, "every triangle in the mesh is degenerate");
+            }
+            _ => panic!("a mesh of degenerate faces should not load"),
+        }
+
+        match load(
+            "nan.obj",
+            &format!(
+                "{}v nan 0 0
+f 1 2 6
+",
+                PENTAGON
+            ),
+        ) {
+            Err(SceneError::Parse { line, key, .. }) => {
+                assert_eq!((line, key), (6, Some(String::from("v"))));
+            }
+            _ => panic!("a `nan` vertex should not load"),
+        }
+    }
+
+    #[test]
+    fn reads_one_component_uvs_and_zero_normals() {
+        let source = format!("{}vt 0.25\nvn 0 0 0\nf 1/1/1 2/1/1 3/1/1\n", PENTAGON);
+        let mesh = load("attributes.obj", &source).unwrap();
+        let ray = Ray::new(Vec3::new(0.8, 1.0, -0.6), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = mesh.hit(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!((rec.u, rec.v), (0.25, 0.0));
+        assert!(rec.normal.is_finite());
+        assert_eq!(rec.normal.y().abs(), 1.0);
+    }
+}
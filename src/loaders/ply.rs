@@ -0,0 +1,682 @@
+use crate::hittables::TriangleMesh;
+use crate::loaders::{SceneError, SharedMaterial};
+use crate::math::Vec3;
+use std::fs;
+use std::path::Path;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Copy, Clone)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(Scalar::I8),
+            "uchar" | "uint8" => Some(Scalar::U8),
+            "short" | "int16" => Some(Scalar::I16),
+            "ushort" | "uint16" => Some(Scalar::U16),
+            "int" | "int32" => Some(Scalar::I32),
+            "uint" | "uint32" => Some(Scalar::U32),
+            "float" | "float32" => Some(Scalar::F32),
+            "double" | "float64" => Some(Scalar::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    fn is_integer(self) -> bool {
+        !matches!(self, Scalar::F32 | Scalar::F64)
+    }
+}
+
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar),
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar(name, _) | Property::List(name, _, _) => name,
+        }
+    }
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    line: usize,
+    properties: Vec<Property>,
+}
+
+struct Body<'a> {
+    format: Format,
+    data: &'a [u8],
+    position: usize,
+    line: usize,
+}
+
+impl<'a> Body<'a> {
+    fn token(&mut self) -> Result<&'a str, String> {
+        loop {
+            while self.position < self.data.len() && self.data[self.position] != b'\n' {
+                if !self.data[self.position].is_ascii_whitespace() {
+                    let start = self.position;
+                    while self.position < self.data.len()
+                        && !self.data[self.position].is_ascii_whitespace()
+                    {
+                        self.position += 1;
+                    }
+                    return std::str::from_utf8(&self.data[start..self.position])
+                        .map_err(|_| String::from("invalid text"));
+                }
+                self.position += 1;
+            }
+            if self.position >= self.data.len() {
+                return Err(String::from("unexpected end of file"));
+            }
+            self.position += 1;
+            self.line += 1;
+        }
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        if self.position + N > self.data.len() {
+            return Err(String::from("unexpected end of file"));
+        }
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&self.data[self.position..self.position + N]);
+        self.position += N;
+        if self.format == Format::BinaryBigEndian {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
+    fn read(&mut self, ty: Scalar) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            let token = self.token()?;
+            return token
+                .parse::<f64>()
+                .map_err(|_| format!("expected a number, got `{}`", token));
+        }
+        // Binary data is always handled as little endian after `bytes` swapped it.
+        Ok(match ty {
+            Scalar::I8 => f64::from(i8::from_le_bytes(self.bytes()?)),
+            Scalar::U8 => f64::from(u8::from_le_bytes(self.bytes()?)),
+            Scalar::I16 => f64::from(i16::from_le_bytes(self.bytes()?)),
+            Scalar::U16 => f64::from(u16::from_le_bytes(self.bytes()?)),
+            Scalar::I32 => f64::from(i32::from_le_bytes(self.bytes()?)),
+            Scalar::U32 => f64::from(u32::from_le_bytes(self.bytes()?)),
+            Scalar::F32 => f64::from(f32::from_le_bytes(self.bytes()?)),
+            Scalar::F64 => f64::from_le_bytes(self.bytes()?),
+        })
+    }
+
+    // List counts and vertex indices, which must be whole numbers that fit a `u32`.
+    fn read_index(&mut self, ty: Scalar) -> Result<u32, String> {
+        let value = self.read(ty)?;
+        if value < 0.0 || value.fract() != 0.0 || value > f64::from(u32::MAX) {
+            return Err(format!("expected a non-negative integer, got `{}`", value));
+        }
+        Ok(value as u32)
+    }
+
+    // How many records of `element` the rest of the data could hold at most, so that a count
+    // in the header can't reserve more memory than the file is able to fill.
+    fn capacity(&self, element: &Element) -> usize {
+        let record: usize = element
+            .properties
+            .iter()
+            .map(|property| match (self.format, property) {
+                // A value and the whitespace after it.
+                (Format::Ascii, _) => 2,
+                (_, Property::Scalar(_, ty)) => ty.size(),
+                (_, Property::List(_, count, _)) => count.size(),
+            })
+            .sum();
+        element
+            .count
+            .min((self.data.len() - self.position) / record.max(1))
+    }
+
+    fn skip(&mut self, ty: Scalar) -> Result<(), String> {
+        if self.format == Format::Ascii {
+            self.token().map(|_| ())
+        } else if self.position + ty.size() > self.data.len() {
+            Err(String::from("unexpected end of file"))
+        } else {
+            self.position += ty.size();
+            Ok(())
+        }
+    }
+}
+
+const VERTEX_PROPERTIES: [&str; 12] = [
+    "x",
+    "y",
+    "z",
+    "nx",
+    "ny",
+    "nz",
+    "u",
+    "v",
+    "s",
+    "t",
+    "texture_u",
+    "texture_v",
+];
+
+/// Loads a PLY file in ascii, binary_little_endian or binary_big_endian format into a
+/// `TriangleMesh`.
+///
+/// The `vertex` element needs `x`, `y`, `z` and may have `nx`/`ny`/`nz` normals and `u`/`v`
+/// (or `s`/`t`, `texture_u`/`texture_v`) coordinates, other scalar properties such as
+/// colours or scanner confidence are skipped. Polygons in the `face` element's
+/// `vertex_indices` list are triangulated as fans, all other elements are ignored.
+pub fn load_ply<P: AsRef<Path>>(
+    path: P,
+    material: SharedMaterial,
+) -> Result<TriangleMesh<SharedMaterial>, SceneError> {
+    let file = path.as_ref();
+    let error = |line: usize, key: Option<&str>, message: String| SceneError::Parse {
+        file: file.to_path_buf(),
+        line,
+        key: key.map(String::from),
+        message,
+    };
+    let data = fs::read(file).map_err(|source| SceneError::Io {
+        file: file.to_path_buf(),
+        source,
+    })?;
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut position = 0;
+    let mut line = 0;
+    loop {
+        let end = match data[position..].iter().position(|&b| b == b'\n') {
+            Some(end) => position + end,
+            None => return Err(error(line, None, String::from("missing `end_header`"))),
+        };
+        let text = String::from_utf8_lossy(&data[position..end]);
+        position = end + 1;
+        line += 1;
+        let mut tokens = text.split_whitespace();
+        let key = match tokens.next() {
+            Some(key) => key,
+            None => continue,
+        };
+        if line == 1 {
+            if key != "ply" {
+                return Err(error(line, None, String::from("not a ply file")));
+            }
+            continue;
+        }
+        let words: Vec<&str> = tokens.collect();
+        match (key, words.as_slice()) {
+            ("comment", _) | ("obj_info", _) => {}
+            ("end_header", _) => break,
+            ("format", [name, _]) => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => {
+                        return Err(error(
+                            line,
+                            Some(key),
+                            format!("unsupported format `{}`", name),
+                        ))
+                    }
+                })
+            }
+            ("element", [name, count]) => {
+                let count = count
+                    .parse()
+                    .map_err(|_| error(line, Some(key), format!("invalid count `{}`", count)))?;
+                elements.push(Element {
+                    name: name.to_string(),
+                    count,
+                    line,
+                    properties: Vec::new(),
+                });
+            }
+            ("property", _) => {
+                let element = match elements.last_mut() {
+                    Some(element) => element,
+                    None => {
+                        return Err(error(
+                            line,
+                            Some(key),
+                            String::from("property outside of an element"),
+                        ))
+                    }
+                };
+                let scalar = |name: &str| {
+                    Scalar::parse(name).ok_or_else(|| {
+                        error(line, Some(key), format!("unsupported type `{}`", name))
+                    })
+                };
+                let property = match words.as_slice() {
+                    ["list", count, item, name] => {
+                        Property::List(name.to_string(), scalar(count)?, scalar(item)?)
+                    }
+                    [ty, name] => Property::Scalar(name.to_string(), scalar(ty)?),
+                    _ => return Err(error(line, Some(key), String::from("malformed property"))),
+                };
+                element.properties.push(property);
+            }
+            _ => {
+                return Err(error(
+                    line,
+                    Some(key),
+                    String::from("unexpected header line"),
+                ))
+            }
+        }
+    }
+    let format = format.ok_or_else(|| error(line, None, String::from("missing `format`")))?;
+
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut uvs: Vec<(f32, f32)> = Vec::new();
+    let mut indices: Vec<[u32; 3]> = Vec::new();
+    let mut body = Body {
+        format,
+        data: &data,
+        position,
+        line,
+    };
+    let body_error = |body: &Body, element: &Element, message: String| {
+        if format == Format::Ascii {
+            error(body.line + 1, Some(&element.name), message)
+        } else {
+            error(
+                0,
+                Some(&element.name),
+                format!("{} at byte {}", message, body.position),
+            )
+        }
+    };
+
+    for element in elements.iter() {
+        match element.name.as_str() {
+            "vertex" => {
+                let names: Vec<&str> = element.properties.iter().map(Property::name).collect();
+                let has = |name: &str| names.contains(&name);
+                for property in element.properties.iter() {
+                    if let Property::List(name, _, _) = property {
+                        if VERTEX_PROPERTIES.contains(&name.as_str()) {
+                            return Err(error(
+                                element.line,
+                                Some(name),
+                                String::from("vertex property must not be a list"),
+                            ));
+                        }
+                    }
+                }
+                if !(has("x") && has("y") && has("z")) {
+                    return Err(error(
+                        element.line,
+                        Some(&element.name),
+                        String::from("vertices need `x`, `y` and `z`"),
+                    ));
+                }
+                let with_normals = has("nx") || has("ny") || has("nz");
+                if with_normals && !(has("nx") && has("ny") && has("nz")) {
+                    return Err(error(
+                        element.line,
+                        Some(&element.name),
+                        String::from("normals need all of `nx`, `ny` and `nz`"),
+                    ));
+                }
+                let mut with_uvs = false;
+                for (u, v) in [("u", "v"), ("s", "t"), ("texture_u", "texture_v")] {
+                    if has(u) != has(v) {
+                        return Err(error(
+                            element.line,
+                            Some(&element.name),
+                            format!("texture coordinates need both `{}` and `{}`", u, v),
+                        ));
+                    }
+                    with_uvs |= has(u);
+                }
+                positions.reserve(body.capacity(element));
+                for _ in 0..element.count {
+                    let mut p = Vec3::default();
+                    let mut n = Vec3::default();
+                    let mut uv = (0.0, 0.0);
+                    for property in element.properties.iter() {
+                        match property {
+                            Property::Scalar(name, ty) => {
+                                let value =
+                                    body.read(*ty).map_err(|m| body_error(&body, element, m))?
+                                        as f32;
+                                match name.as_str() {
+                                    "x" => p[0] = value,
+                                    "y" => p[1] = value,
+                                    "z" => p[2] = value,
+                                    "nx" => n[0] = value,
+                                    "ny" => n[1] = value,
+                                    "nz" => n[2] = value,
+                                    "u" | "s" | "texture_u" => uv.0 = value,
+                                    "v" | "t" | "texture_v" => uv.1 = value,
+                                    _ => {}
+                                }
+                            }
+                            Property::List(_, count, item) => {
+                                let count = body
+                                    .read_index(*count)
+                                    .map_err(|m| body_error(&body, element, m))?;
+                                for _ in 0..count {
+                                    body.skip(*item)
+                                        .map_err(|m| body_error(&body, element, m))?;
+                                }
+                            }
+                        }
+                    }
+                    if !p.is_finite() {
+                        return Err(body_error(
+                            &body,
+                            element,
+                            String::from("vertex position is not finite"),
+                        ));
+                    }
+                    positions.push(p);
+                    if with_normals {
+                        if !n.is_finite() {
+                            return Err(body_error(
+                                &body,
+                                element,
+                                String::from("vertex normal is not finite"),
+                            ));
+                        }
+                        // Zero normals stay zero, the mesh shades those with the face normal.
+                        normals.push(if n.squared_length() > 0.0 {
+                            n.unit_vector()
+                        } else {
+                            n
+                        });
+                    }
+                    if with_uvs {
+                        uvs.push(uv);
+                    }
+                }
+            }
+            "face" => {
+                let list = element.properties.iter().position(|property| {
+                    matches!(property, Property::List(name, _, _)
+                        if name == "vertex_indices" || name == "vertex_index")
+                });
+                let list = match list {
+                    Some(list) => list,
+                    None => {
+                        return Err(error(
+                            element.line,
+                            Some(&element.name),
+                            String::from("faces need a `vertex_indices` list"),
+                        ))
+                    }
+                };
+                if let Property::List(name, _, item) = &element.properties[list] {
+                    if !item.is_integer() {
+                        return Err(error(
+                            element.line,
+                            Some(name),
+                            String::from("vertex indices must be integers"),
+                        ));
+                    }
+                }
+                indices.reserve(body.capacity(element));
+                let mut polygon: Vec<u32> = Vec::with_capacity(4);
+                for _ in 0..element.count {
+                    for (index, property) in element.properties.iter().enumerate() {
+                        match property {
+                            Property::Scalar(_, ty) => {
+                                body.skip(*ty).map_err(|m| body_error(&body, element, m))?
+                            }
+                            Property::List(_, count, item) => {
+                                let count = body
+                                    .read_index(*count)
+                                    .map_err(|m| body_error(&body, element, m))?;
+                                polygon.clear();
+                                for _ in 0..count {
+                                    if index == list {
+                                        let value = body
+                                            .read_index(*item)
+                                            .map_err(|m| body_error(&body, element, m))?;
+                                        polygon.push(value);
+                                    } else {
+                                        body.skip(*item)
+                                            .map_err(|m| body_error(&body, element, m))?;
+                                    }
+                                }
+                                if index != list {
+                                    continue;
+                                }
+                                if polygon.len() < 3 {
+                                    return Err(body_error(
+                                        &body,
+                                        element,
+                                        String::from("a face needs 3 vertices"),
+                                    ));
+                                }
+                                if polygon.iter().any(|&i| i as usize >= positions.len()) {
+                                    return Err(body_error(
+                                        &body,
+                                        element,
+                                        String::from("vertex index out of range"),
+                                    ));
+                                }
+                                for i in 1..polygon.len() - 1 {
+                                    indices.push([polygon[0], polygon[i], polygon[i + 1]]);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    for property in element.properties.iter() {
+                        match property {
+                            Property::Scalar(_, ty) => body.skip(*ty),
+                            Property::List(_, count, item) => body
+                                .read_index(*count)
+                                .and_then(|count| (0..count).try_for_each(|_| body.skip(*item))),
+                        }
+                        .map_err(|m| body_error(&body, element, m))?;
+                    }
+                }
+            }
+        }
+    }
+
+    if indices.is_empty() {
+        return Err(error(0, None, String::from("no faces in file")));
+    }
+    TriangleMesh::new(positions, normals, uvs, indices, material)
+        .map_err(|message| error(0, None, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittables::Hitable;
+    use crate::loaders::{test_file, test_material};
+    use crate::physics::Ray;
+
+    // A unit square in the y = 0 plane as one quad, plus a triangle off to the side.
+    const VERTICES: [[f32; 3]; 7] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 0.0, 1.0],
+        [0.0, 0.0, 1.0],
+        [3.0, 0.0, 0.0],
+        [4.0, 0.0, 0.0],
+        [3.0, 0.0, 1.0],
+    ];
+    const FACES: [&[i32]; 2] = [&[0, 1, 2, 3], &[4, 5, 6]];
+
+    fn header(format: &str) -> String {
+        format!(
+            "ply\nformat {} 1.0\nelement vertex {}\nproperty float x\nproperty float y\n\
+             property float z\nproperty float confidence\nelement face {}\n\
+             property list uchar int vertex_indices\nend_header\n",
+            format,
+            VERTICES.len(),
+            FACES.len()
+        )
+    }
+
+    fn ascii() -> Vec<u8> {
+        let mut text = header("ascii");
+        for [x, y, z] in VERTICES.iter() {
+            text += &format!("{} {} {} 0.5\n", x, y, z);
+        }
+        for face in FACES.iter() {
+            let indices: Vec<String> = face.iter().map(|i| i.to_string()).collect();
+            text += &format!("{} {}\n", face.len(), indices.join(" "));
+        }
+        text.into_bytes()
+    }
+
+    fn binary(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian {
+            "binary_big_endian"
+        } else {
+            "binary_little_endian"
+        };
+        let mut data = header(format).into_bytes();
+        for vertex in VERTICES.iter() {
+            for value in vertex.iter().chain([0.5].iter()) {
+                if big_endian {
+                    data.extend_from_slice(&value.to_be_bytes());
+                } else {
+                    data.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+        for face in FACES.iter() {
+            data.push(face.len() as u8);
+            for index in face.iter() {
+                if big_endian {
+                    data.extend_from_slice(&index.to_be_bytes());
+                } else {
+                    data.extend_from_slice(&index.to_le_bytes());
+                }
+            }
+        }
+        data
+    }
+
+    fn load(name: &str, data: &[u8]) -> Result<TriangleMesh<SharedMaterial>, SceneError> {
+        load_ply(test_file(name, data), test_material())
+    }
+
+    // Distance to the mesh of a ray straight down onto the y = 0 plane at `x`, `z`.
+    fn probe(mesh: &TriangleMesh<SharedMaterial>, x: f32, z: f32) -> Option<f32> {
+        let ray = Ray::new(Vec3::new(x, 1.0, z), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        mesh.hit(&ray, 0.001, f32::MAX).map(|rec| rec.t)
+    }
+
+    #[test]
+    fn ascii_and_binary_load_the_same_mesh() {
+        let ascii = load("square_ascii.ply", &ascii()).unwrap();
+        let little = load("square_little_endian.ply", &binary(false)).unwrap();
+        let big = load("square_big_endian.ply", &binary(true)).unwrap();
+        let probes = [(0.2, 0.7), (0.7, 0.2), (3.2, 0.2), (3.8, 0.8), (2.0, 0.5)];
+        for &(x, z) in probes.iter() {
+            assert_eq!(probe(&ascii, x, z), probe(&little, x, z), "at {}, {}", x, z);
+            assert_eq!(probe(&ascii, x, z), probe(&big, x, z), "at {}, {}", x, z);
+        }
+        assert_eq!(probe(&ascii, 0.2, 0.7), Some(1.0));
+        assert_eq!(probe(&ascii, 0.7, 0.2), Some(1.0));
+        assert_eq!(probe(&ascii, 3.8, 0.8), None);
+    }
+
+    #[test]
+    fn rejects_invalid_indices() {
+        let ascii = String::from_utf8(ascii()).unwrap();
+        let first_face = 11 + VERTICES.len();
+        for (name, face) in [
+            ("negative.ply", "3 0 -1 2"),
+            ("fraction.ply", "3 0 1.5 2"),
+            ("range.ply", "3 0 1 7"),
+        ] {
+            let data = ascii.replacen("4 0 1 2 3", face, 1);
+            match load(name, data.as_bytes()) {
+                Err(SceneError::Parse { line, .. }) => assert_eq!(line, first_face, "{}", face),
+                _ => panic!("`{}` should not load", face),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_counts_the_data_cannot_hold() {
+        let header = header("binary_little_endian");
+        let mut data = binary(false);
+        let huge = header.replacen("element vertex 7", "element vertex 4000000000000", 1);
+        data.splice(..header.len(), huge.into_bytes());
+        match load("huge.ply", &data) {
+            Err(SceneError::Parse { message, .. }) => {
+                assert!(message.starts_with("unexpected end of file"), "{}", message);
+            }
+            _ => panic!("a vertex count past the end of the file should not load"),
+        }
+    }
+
+    #[test]
+    fn needs_both_texture_coordinates() {
+        let ascii = String::from_utf8(ascii()).unwrap();
+        let data = ascii.replacen("property float confidence", "property float u", 1);
+        match load("uv.ply", data.as_bytes()) {
+            Err(SceneError::Parse { line, message, .. }) => {
+                assert_eq!(line, 3);
+                assert_eq!(message, "texture coordinates need both `u` and `v`");
+            }
+            _ => panic!("`u` without `v` should not load"),
+        }
+    }
+
+    #[test]
+    fn shades_zero_normals_with_the_face_normal() {
+        let ascii = String::from_utf8(ascii()).unwrap();
+        let data = ascii
+            .replacen(
+                "property float confidence",
+                "property float nx\nproperty float ny\nproperty float nz",
+                1,
+            )
+            .replace(" 0.5\n", " 0 0 0\n");
+        let mesh = load("normals.ply", data.as_bytes()).unwrap();
+        let ray = Ray::new(Vec3::new(0.2, 1.0, 0.7), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let normal = mesh.hit(&ray, 0.001, f32::MAX).unwrap().normal;
+        assert!(normal.is_finite());
+        assert_eq!(normal.y().abs(), 1.0);
+    }
+}
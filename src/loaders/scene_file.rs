@@ -0,0 +1,1317 @@
+use crate::hittables::{
+    BVHStats, BoxShape, ConstantMedium, Cutout, FlipNormal, Hitable, HittableList, LinearBVH,
+    MovingSphere, RotateY, Sphere, SplitMethod, Transform, Translate, Triangle, TriangleMesh,
+    XYRect, XZRect, YZRect, BVH,
+};
+use crate::integrators::{
+    AmbientOcclusion, DebugIntegrator, DebugMode, DirectLighting, Integrator, MisHeuristic,
+    PathTracer,
+};
+use crate::lights::{DeltaLight, DirectionalLight, EnvironmentLight, PointLight, SpotLight};
+use crate::loaders::{load_obj, load_ply, SharedMaterial, SharedTexture};
+use crate::materials::{
+    BumpMap, Coated, Conductor, Dielectric, DiffuseLight, Isotropic, Lambertian, Metal,
+    MixMaterial, NormalMap, Principled, RoughDielectric,
+};
+use crate::math::{Matrix4, Vec3};
+use crate::physics::{blackbody, Camera};
+use crate::rand::prelude::*;
+use crate::scene::{RenderSettings, Scene};
+use crate::textures::{CheckerTexture, ConstantTexture, ImageTexture, NoiseTexture};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        file: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        file: PathBuf,
+        line: usize,
+        key: Option<String>,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io { file, source } => write!(f, "{}: {}", file.display(), source),
+            SceneError::Parse {
+                file,
+                line,
+                key,
+                message,
+            } => {
+                write!(f, "{}", file.display())?;
+                if *line > 0 {
+                    write!(f, ":{}", line)?;
+                }
+                if let Some(key) = key {
+                    write!(f, ": `{}`", key)?;
+                }
+                write!(f, ": {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+/// Memory layout used for the BVHs of a scene.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BVHLayout {
+    /// `BVH`, a tree of boxed nodes.
+    Boxed,
+    /// `LinearBVH`, a flattened node array.
+    Linear,
+}
+
+/// Reads a scene description and builds a renderable `Scene` from it.
+///
+/// The format is line based. Every statement starts with a keyword followed by
+/// positional words and `key=value` pairs; vectors are written as `x,y,z` and
+/// `#` starts a comment:
+///
+/// ```text
+/// render width=555 height=555 samples=100
+/// camera lookfrom=278,278,-800 lookat=278,278,0 vfov=40
+/// texture checks checker odd=dark even=light
+/// material white lambertian color=0.73,0.73,0.73
+/// object ball sphere center=0,0,0 radius=1 material=white
+/// add ball
+/// ```
+///
+/// Objects are only templates: every `add` or reference from another object
+/// builds a fresh instance, so one definition can be placed several times.
+/// Relative file paths are resolved against the directory of the scene file.
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+    load_scene_with_layout(path, None).map(|(scene, _)| scene)
+}
+
+/// Tree quality of one BVH the scene built, for `load_scene_with_layout` callers to report.
+pub struct BVHReport {
+    pub name: String,
+    pub layout: BVHLayout,
+    pub stats: BVHStats,
+}
+
+impl fmt::Display for BVHReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let layout = match self.layout {
+            BVHLayout::Boxed => "boxed",
+            BVHLayout::Linear => "linear",
+        };
+        write!(f, "bvh {} ({}): {}", self.name, layout, self.stats)
+    }
+}
+
+/// Like `load_scene`, but builds every BVH with `layout` instead of the one the scene asks for
+/// and also returns the stats of every BVH it built.
+pub fn load_scene_with_layout<P: AsRef<Path>>(
+    path: P,
+    layout: Option<BVHLayout>,
+) -> Result<(Scene, Vec<BVHReport>), SceneError> {
+    let file = path.as_ref().to_path_buf();
+    let source = fs::read_to_string(&file).map_err(|source| SceneError::Io {
+        file: file.clone(),
+        source,
+    })?;
+    let mut parser = SceneParser::new(file, layout);
+    for (index, line) in source.lines().enumerate() {
+        if let Some(statement) = parser.tokenize(index + 1, line)? {
+            parser.statement(statement)?;
+        }
+    }
+    parser.finish()
+}
+
+struct Statement {
+    line: usize,
+    keyword: String,
+    words: Vec<String>,
+    params: Vec<(String, String)>,
+}
+
+struct Params<'a> {
+    file: &'a Path,
+    line: usize,
+    values: &'a [(String, String)],
+    used: Vec<bool>,
+}
+
+impl<'a> Params<'a> {
+    fn error(&self, key: &str, message: String) -> SceneError {
+        SceneError::Parse {
+            file: self.file.to_path_buf(),
+            line: self.line,
+            key: Some(key.to_string()),
+            message,
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<&'a str> {
+        let index = self.values.iter().position(|(k, _)| k == key)?;
+        self.used[index] = true;
+        Some(self.values[index].1.as_str())
+    }
+
+    fn has(&self, key: &str) -> bool {
+        self.values.iter().any(|(k, _)| k == key)
+    }
+
+    fn str(&mut self, key: &str) -> Result<&'a str, SceneError> {
+        self.get(key)
+            .ok_or_else(|| self.error(key, String::from("missing required key")))
+    }
+
+    fn parse<T: FromStr>(&mut self, key: &str, raw: &str) -> Result<T, SceneError> {
+        raw.parse::<T>()
+            .map_err(|_| self.error(key, format!("expected a number, got `{}`", raw)))
+    }
+
+    fn number<T: FromStr>(&mut self, key: &str) -> Result<T, SceneError> {
+        let raw = self.str(key)?;
+        self.parse(key, raw)
+    }
+
+    fn number_or<T: FromStr>(&mut self, key: &str, default: T) -> Result<T, SceneError> {
+        match self.get(key) {
+            Some(raw) => self.parse(key, raw),
+            None => Ok(default),
+        }
+    }
+
+    fn boolean(&mut self, key: &str, default: bool) -> Result<bool, SceneError> {
+        match self.get(key) {
+            None => Ok(default),
+            Some("true") => Ok(true),
+            Some("false") => Ok(false),
+            Some(raw) => Err(self.error(key, format!("expected `true` or `false`, got `{}`", raw))),
+        }
+    }
+
+    fn vec3(&mut self, key: &str) -> Result<Vec3, SceneError> {
+        let raw = self.str(key)?;
+        let parts: Vec<&str> = raw.split(',').collect();
+        if parts.len() != 3 {
+            return Err(self.error(key, format!("expected a vector `x,y,z`, got `{}`", raw)));
+        }
+        let mut v = Vec3::default();
+        for (i, part) in parts.iter().enumerate() {
+            v[i] = self.parse(key, part.trim())?;
+        }
+        Ok(v)
+    }
+
+    fn vec3_or(&mut self, key: &str, default: Vec3) -> Result<Vec3, SceneError> {
+        if self.has(key) {
+            self.vec3(key)
+        } else {
+            Ok(default)
+        }
+    }
+
+    fn numbers<T: FromStr>(&mut self, key: &str, group: usize) -> Result<Vec<T>, SceneError> {
+        let raw = self.str(key)?;
+        let numbers = raw
+            .split(',')
+            .map(|part| self.parse(key, part.trim()))
+            .collect::<Result<Vec<T>, SceneError>>()?;
+        if numbers.is_empty() || numbers.len() % group != 0 {
+            return Err(self.error(
+                key,
+                format!(
+                    "expected a multiple of {} numbers, got {}",
+                    group,
+                    numbers.len()
+                ),
+            ));
+        }
+        Ok(numbers)
+    }
+
+    fn floats<const N: usize>(&mut self, key: &str) -> Result<[f32; N], SceneError> {
+        let numbers = self.numbers::<f32>(key, 1)?;
+        if numbers.len() != N {
+            return Err(self.error(
+                key,
+                format!("expected {} numbers, got {}", N, numbers.len()),
+            ));
+        }
+        let mut values = [0.0; N];
+        values.copy_from_slice(&numbers);
+        Ok(values)
+    }
+
+    fn keys(&self) -> Vec<&'a str> {
+        self.values.iter().map(|(k, _)| k.as_str()).collect()
+    }
+
+    fn names(&mut self, key: &str) -> Result<Vec<&'a str>, SceneError> {
+        Ok(self.str(key)?.split(',').map(str::trim).collect())
+    }
+
+    fn finish(self) -> Result<(), SceneError> {
+        match self.used.iter().position(|used| !used) {
+            Some(index) => Err(self.error(&self.values[index].0, String::from("unknown key"))),
+            None => Ok(()),
+        }
+    }
+}
+
+struct SceneParser {
+    file: PathBuf,
+    render: Option<Statement>,
+    camera: Option<Statement>,
+    integrator: Option<Statement>,
+    environment: Option<Statement>,
+    textures: HashMap<String, SharedTexture>,
+    materials: HashMap<String, SharedMaterial>,
+    objects: HashMap<String, Statement>,
+    placed: Vec<(usize, String)>,
+    lights: Vec<(usize, String)>,
+    delta_lights: Vec<Box<dyn DeltaLight>>,
+    layout: Option<BVHLayout>,
+    reports: RefCell<Vec<BVHReport>>,
+    time0: f32,
+    time1: f32,
+}
+
+impl SceneParser {
+    fn new(file: PathBuf, layout: Option<BVHLayout>) -> Self {
+        SceneParser {
+            file,
+            layout,
+            reports: RefCell::new(Vec::new()),
+            render: None,
+            camera: None,
+            integrator: None,
+            environment: None,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            objects: HashMap::new(),
+            placed: Vec::new(),
+            lights: Vec::new(),
+            delta_lights: Vec::new(),
+            time0: 0.0,
+            time1: 1.0,
+        }
+    }
+
+    fn error(&self, line: usize, message: String) -> SceneError {
+        SceneError::Parse {
+            file: self.file.clone(),
+            line,
+            key: None,
+            message,
+        }
+    }
+
+    fn params<'a>(&'a self, statement: &'a Statement) -> Params<'a> {
+        Params {
+            file: &self.file,
+            line: statement.line,
+            values: &statement.params,
+            used: vec![false; statement.params.len()],
+        }
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        match self.file.parent() {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        }
+    }
+
+    fn tokenize(&self, line: usize, text: &str) -> Result<Option<Statement>, SceneError> {
+        let text = match text.find('#') {
+            Some(index) => &text[..index],
+            None => text,
+        };
+        let mut tokens = text.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword.to_string(),
+            None => return Ok(None),
+        };
+        let mut words = Vec::new();
+        let mut params: Vec<(String, String)> = Vec::new();
+        for token in tokens {
+            match token.find('=') {
+                Some(index) => {
+                    let key = &token[..index];
+                    if params.iter().any(|(k, _)| k == key) {
+                        return Err(SceneError::Parse {
+                            file: self.file.clone(),
+                            line,
+                            key: Some(key.to_string()),
+                            message: String::from("key given more than once"),
+                        });
+                    }
+                    params.push((key.to_string(), token[index + 1..].to_string()));
+                }
+                None if params.is_empty() => words.push(token.to_string()),
+                None => {
+                    return Err(self.error(
+                        line,
+                        format!("`{}` must come before the `key=value` pairs", token),
+                    ))
+                }
+            }
+        }
+        Ok(Some(Statement {
+            line,
+            keyword,
+            words,
+            params,
+        }))
+    }
+
+    fn declaration<'a>(
+        &self,
+        statement: &'a Statement,
+        what: &str,
+    ) -> Result<(&'a str, &'a str), SceneError> {
+        match statement.words.as_slice() {
+            [name, kind] => Ok((name, kind)),
+            _ => Err(self.error(statement.line, format!("expected `{} <name> <type>`", what))),
+        }
+    }
+
+    // Where the statements that configure the whole scene are kept.
+    fn setting(&mut self, keyword: &str) -> &mut Option<Statement> {
+        match keyword {
+            "render" => &mut self.render,
+            "camera" => &mut self.camera,
+            "integrator" => &mut self.integrator,
+            _ => &mut self.environment,
+        }
+    }
+
+    fn statement(&mut self, statement: Statement) -> Result<(), SceneError> {
+        match statement.keyword.as_str() {
+            "render" | "camera" | "integrator" | "environment" => {
+                let keyword = statement.keyword.clone();
+                if let Some(first) = self.setting(&keyword) {
+                    let first = first.line;
+                    return Err(self.error(
+                        statement.line,
+                        format!("`{}` is already given on line {}", keyword, first),
+                    ));
+                }
+                *self.setting(&keyword) = Some(statement);
+            }
+            "texture" => {
+                let (name, _) = self.declaration(&statement, "texture")?;
+                let texture = self.build_texture(&statement)?;
+                self.textures.insert(name.to_string(), texture);
+            }
+            "material" => {
+                let (name, _) = self.declaration(&statement, "material")?;
+                let material = self.build_material(&statement)?;
+                self.materials.insert(name.to_string(), material);
+            }
+            "object" => {
+                let (name, _) = self.declaration(&statement, "object")?;
+                if let Some(first) = self.objects.get(name) {
+                    return Err(self.error(
+                        statement.line,
+                        format!(
+                            "object `{}` is already defined on line {}",
+                            name, first.line
+                        ),
+                    ));
+                }
+                self.objects.insert(name.to_string(), statement);
+            }
+            // With parameters, `light` declares a light that isn't an object.
+            "light" if !statement.params.is_empty() => {
+                let light = self.build_delta_light(&statement)?;
+                self.delta_lights.push(light);
+            }
+            "add" | "light" => {
+                if statement.words.is_empty() || !statement.params.is_empty() {
+                    return Err(self.error(
+                        statement.line,
+                        format!("expected `{} <name>...`", statement.keyword),
+                    ));
+                }
+                for name in statement.words.iter() {
+                    self.placed.push((statement.line, name.clone()));
+                    if statement.keyword == "light" {
+                        self.lights.push((statement.line, name.clone()));
+                    }
+                }
+            }
+            keyword => {
+                return Err(self.error(statement.line, format!("unknown statement `{}`", keyword)))
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(Scene, Vec<BVHReport>), SceneError> {
+        let defaults = RenderSettings::default();
+        let settings = match &self.render {
+            Some(statement) => {
+                let mut p = self.params(statement);
+                let settings = RenderSettings {
+                    width: p.number_or("width", defaults.width)?,
+                    height: p.number_or("height", defaults.height)?,
+                    rays_per_pixel: p.number_or("samples", defaults.rays_per_pixel)?,
+                    max_depth: p.number_or("max_depth", defaults.max_depth)?,
+                    roulette_depth: p.number_or("roulette_depth", defaults.roulette_depth)?,
+                };
+                p.finish()?;
+                settings
+            }
+            None => defaults,
+        };
+        let camera = match &self.camera {
+            Some(statement) => {
+                let mut p = self.params(statement);
+                let lookfrom = p.vec3("lookfrom")?;
+                let lookat = p.vec3("lookat")?;
+                let vup = p.vec3_or("vup", Vec3::new(0.0, 1.0, 0.0))?;
+                let vfov = p.number("vfov")?;
+                let aperture = p.number_or("aperture", 0.0)?;
+                let focus_dist = p.number_or("focus_dist", 10.0)?;
+                let time0 = p.number_or("time0", 0.0)?;
+                let time1 = p.number_or("time1", 1.0)?;
+                p.finish()?;
+                self.time0 = time0;
+                self.time1 = time1;
+                Camera::new(
+                    lookfrom,
+                    lookat,
+                    vup,
+                    vfov,
+                    settings.width as f32 / settings.height as f32,
+                    aperture,
+                    focus_dist,
+                    time0,
+                    time1,
+                )
+            }
+            None => return Err(self.error(0, String::from("scene has no `camera` statement"))),
+        };
+
+        let integrator = match &self.integrator {
+            Some(statement) => self.build_integrator(statement)?,
+            None => Box::new(PathTracer::default()),
+        };
+
+        let environment = match &self.environment {
+            Some(statement) => Some(self.build_environment(statement)?),
+            None => None,
+        };
+
+        let mut world = HittableList::new(Vec::with_capacity(self.placed.len()));
+        for (line, name) in self.placed.iter() {
+            match self.instantiate(*line, name) {
+                Some(object) => world.entities.push(object?),
+                None => return Err(self.error(*line, format!("unknown object `{}`", name))),
+            }
+        }
+        // Lights get their own instance, the one in the world is only hit.
+        let mut lights = HittableList::new(Vec::with_capacity(self.lights.len()));
+        for (line, name) in self.lights.iter() {
+            let light = self.instantiate(*line, name).unwrap()?;
+            if light.sample(&Vec3::default()).is_none() {
+                return Err(self.error(
+                    *line,
+                    format!("object `{}` can't be sampled as a light", name),
+                ));
+            }
+            lights.entities.push(light);
+        }
+        let scene = Scene::new(
+            camera,
+            world,
+            lights,
+            self.delta_lights,
+            environment,
+            integrator,
+            settings,
+        );
+        Ok((scene, self.reports.into_inner()))
+    }
+
+    fn build_integrator(&self, statement: &Statement) -> Result<Box<dyn Integrator>, SceneError> {
+        let mut p = self.params(statement);
+        let kind = match statement.words.as_slice() {
+            [kind] => kind.as_str(),
+            _ => {
+                return Err(self.error(statement.line, String::from("expected `integrator <type>`")))
+            }
+        };
+        let integrator: Box<dyn Integrator> = match kind {
+            "path" => Box::new(PathTracer::new(self.heuristic(&mut p)?)),
+            "direct" => Box::new(DirectLighting::new(self.heuristic(&mut p)?)),
+            "ao" => {
+                let distance = p.number_or("distance", 1.0)?;
+                let cosine_sampling = match p.get("sampling") {
+                    None | Some("cosine") => true,
+                    Some("uniform") => false,
+                    Some(sampling) => {
+                        return Err(p.error(
+                            "sampling",
+                            format!("expected `cosine` or `uniform`, got `{}`", sampling),
+                        ))
+                    }
+                };
+                Box::new(AmbientOcclusion::new(distance, cosine_sampling))
+            }
+            "debug" => {
+                let mode = match p.str("mode")? {
+                    "normal" => DebugMode::Normal,
+                    "uv" => DebugMode::Uv,
+                    "albedo" => DebugMode::Albedo,
+                    mode => {
+                        return Err(p.error(
+                            "mode",
+                            format!("expected `normal`, `uv` or `albedo`, got `{}`", mode),
+                        ))
+                    }
+                };
+                Box::new(DebugIntegrator::new(mode))
+            }
+            kind => {
+                return Err(self.error(statement.line, format!("unknown integrator `{}`", kind)))
+            }
+        };
+        p.finish()?;
+        Ok(integrator)
+    }
+
+    fn heuristic(&self, p: &mut Params) -> Result<Option<MisHeuristic>, SceneError> {
+        match p.get("mis") {
+            None | Some("power") => Ok(Some(MisHeuristic::Power)),
+            Some("balance") => Ok(Some(MisHeuristic::Balance)),
+            Some("none") => Ok(None),
+            Some(mis) => Err(p.error(
+                "mis",
+                format!("expected `power`, `balance` or `none`, got `{}`", mis),
+            )),
+        }
+    }
+
+    fn build_texture(&self, statement: &Statement) -> Result<SharedTexture, SceneError> {
+        let (_, kind) = self.declaration(statement, "texture")?;
+        let mut p = self.params(statement);
+        let texture: SharedTexture = match kind {
+            "constant" => Arc::new(ConstantTexture::new(p.vec3("color")?)),
+            "checker" => Arc::new(CheckerTexture::new(
+                self.texture(&mut p, "odd")?,
+                self.texture(&mut p, "even")?,
+            )),
+            "noise" => Arc::new(NoiseTexture::new(p.number("scale")?)),
+            "image" => {
+                let file = p.str("file")?;
+                Arc::new(
+                    ImageTexture::open(self.resolve(file))
+                        .map_err(|e| p.error("file", e.to_string()))?,
+                )
+            }
+            _ => return Err(self.error(statement.line, format!("unknown texture type `{}`", kind))),
+        };
+        p.finish()?;
+        Ok(texture)
+    }
+
+    fn build_material(&self, statement: &Statement) -> Result<SharedMaterial, SceneError> {
+        let (_, kind) = self.declaration(statement, "material")?;
+        let mut p = self.params(statement);
+        let material: SharedMaterial = match kind {
+            "lambertian" => Arc::new(Lambertian::new(self.texture_or_color(&mut p)?)),
+            "metal" => Arc::new(Metal::new(p.vec3("albedo")?, p.number_or("fuzz", 0.0)?)),
+            "conductor" => {
+                let roughness = self.scalar_texture(&mut p, "roughness", 0.0)?;
+                match p.get("preset") {
+                    None => Arc::new(Conductor::new(roughness, p.vec3("eta")?, p.vec3("k")?)),
+                    Some("gold") => Arc::new(Conductor::gold(roughness)),
+                    Some("copper") => Arc::new(Conductor::copper(roughness)),
+                    Some("aluminium") => Arc::new(Conductor::aluminium(roughness)),
+                    Some("silver") => Arc::new(Conductor::silver(roughness)),
+                    Some(preset) => {
+                        return Err(p.error(
+                            "preset",
+                            format!(
+                                "expected `gold`, `copper`, `aluminium` or `silver`, got `{}`",
+                                preset
+                            ),
+                        ))
+                    }
+                }
+            }
+            "dielectric" => {
+                let ref_idx = p.number("ref_idx")?;
+                if p.has("transmittance") {
+                    let transmittance = p.vec3("transmittance")?;
+                    let distance: f32 = p.number_or("distance", 1.0)?;
+                    if distance <= 0.0 || !distance.is_finite() {
+                        return Err(p.error("distance", String::from("must be positive")));
+                    }
+                    Arc::new(Dielectric::with_absorption(
+                        ref_idx,
+                        transmittance,
+                        distance,
+                    ))
+                } else {
+                    Arc::new(Dielectric::new(ref_idx))
+                }
+            }
+            "rough_dielectric" => Arc::new(RoughDielectric::new(
+                self.scalar_texture(&mut p, "roughness", 0.0)?,
+                p.number("ref_idx")?,
+                p.vec3_or("tint", Vec3::new(1.0, 1.0, 1.0))?,
+            )),
+            "principled" => Arc::new(
+                Principled::new(self.texture_or_color(&mut p)?)
+                    .metallic(self.scalar_texture(&mut p, "metallic", 0.0)?)
+                    .roughness(self.scalar_texture(&mut p, "roughness", 0.5)?)
+                    .specular(self.scalar_texture(&mut p, "specular", 0.5)?)
+                    .clearcoat(self.scalar_texture(&mut p, "clearcoat", 0.0)?)
+                    .sheen(self.scalar_texture(&mut p, "sheen", 0.0)?)
+                    .transmission(self.scalar_texture(&mut p, "transmission", 0.0)?)
+                    .emission(self.color_texture(&mut p, "emission", Vec3::default())?),
+            ),
+            "mix" => Arc::new(MixMaterial::new(
+                self.named_material(&mut p, "a")?,
+                self.named_material(&mut p, "b")?,
+                self.scalar_texture(&mut p, "mask", 0.5)?,
+            )),
+            "coated" => Arc::new(Coated::new(
+                self.named_material(&mut p, "base")?,
+                p.number_or("ref_idx", 1.5)?,
+                p.number_or("roughness", 0.0)?,
+            )),
+            "normal_map" => Arc::new(NormalMap::new(
+                self.named_material(&mut p, "base")?,
+                self.texture(&mut p, "texture")?,
+            )),
+            "bump_map" => Arc::new(BumpMap::new(
+                self.named_material(&mut p, "base")?,
+                self.texture(&mut p, "texture")?,
+                p.number_or("scale", 1.0)?,
+            )),
+            "diffuse_light" => {
+                let emit: SharedTexture = if p.has("temperature") {
+                    Arc::new(ConstantTexture::new(blackbody(p.number("temperature")?)))
+                } else {
+                    self.texture_or_color(&mut p)?
+                };
+                Arc::new(DiffuseLight::with_options(
+                    emit,
+                    p.number_or("intensity", 1.0)?,
+                    p.boolean("two_sided", true)?,
+                ))
+            }
+            "isotropic" => Arc::new(Isotropic::new(self.texture_or_color(&mut p)?)),
+            _ => {
+                return Err(self.error(statement.line, format!("unknown material type `{}`", kind)))
+            }
+        };
+        p.finish()?;
+        Ok(material)
+    }
+
+    fn build_environment(&self, statement: &Statement) -> Result<EnvironmentLight, SceneError> {
+        let mut p = self.params(statement);
+        let intensity = p.number_or("intensity", 1.0)?;
+        let environment = if p.has("file") {
+            let file = p.str("file")?;
+            let rotation = p.number_or("rotation", 0.0)?;
+            EnvironmentLight::open(self.resolve(file), intensity, rotation)
+                .map_err(|e| p.error("file", e.to_string()))?
+        } else {
+            EnvironmentLight::constant(p.vec3("color")? * intensity)
+        };
+        p.finish()?;
+        Ok(environment)
+    }
+
+    fn build_delta_light(&self, statement: &Statement) -> Result<Box<dyn DeltaLight>, SceneError> {
+        let (_, kind) = self.declaration(statement, "light")?;
+        let mut p = self.params(statement);
+        let color = if p.has("temperature") {
+            blackbody(p.number("temperature")?)
+        } else {
+            p.vec3_or("color", Vec3::new(1.0, 1.0, 1.0))?
+        };
+        let intensity = color * p.number_or("intensity", 1.0)?;
+        let light: Box<dyn DeltaLight> = match kind {
+            "point" => Box::new(PointLight::new(p.vec3("position")?, intensity)),
+            "spot" => {
+                let angle = p.number_or("angle", 30.0)?;
+                Box::new(SpotLight::new(
+                    p.vec3("position")?,
+                    p.vec3("direction")?,
+                    intensity,
+                    angle,
+                    p.number_or("falloff", angle)?,
+                ))
+            }
+            "directional" => Box::new(DirectionalLight::new(
+                p.vec3("direction")?,
+                intensity,
+                p.number_or("angular_diameter", 0.0)?,
+            )),
+            _ => return Err(self.error(statement.line, format!("unknown light type `{}`", kind))),
+        };
+        p.finish()?;
+        Ok(light)
+    }
+
+    fn texture(&self, p: &mut Params, key: &str) -> Result<SharedTexture, SceneError> {
+        let name = p.str(key)?;
+        self.textures
+            .get(name)
+            .cloned()
+            .ok_or_else(|| p.error(key, format!("unknown texture `{}`", name)))
+    }
+
+    fn texture_or_color(&self, p: &mut Params) -> Result<SharedTexture, SceneError> {
+        if p.has("texture") {
+            self.texture(p, "texture")
+        } else {
+            Ok(Arc::new(ConstantTexture::new(p.vec3("color")?)))
+        }
+    }
+
+    // A number for a constant value, otherwise the name of a texture.
+    fn scalar_texture(
+        &self,
+        p: &mut Params,
+        key: &str,
+        default: f32,
+    ) -> Result<SharedTexture, SceneError> {
+        match p.get(key) {
+            None => Ok(Arc::new(ConstantTexture::new(Vec3::new(
+                default, default, default,
+            )))),
+            Some(value) => match value.parse::<f32>() {
+                Ok(v) => Ok(Arc::new(ConstantTexture::new(Vec3::new(v, v, v)))),
+                Err(_) => self.texture(p, key),
+            },
+        }
+    }
+
+    // A colour `r,g,b` for a constant value, otherwise the name of a texture.
+    fn color_texture(
+        &self,
+        p: &mut Params,
+        key: &str,
+        default: Vec3,
+    ) -> Result<SharedTexture, SceneError> {
+        match p.get(key) {
+            None => Ok(Arc::new(ConstantTexture::new(default))),
+            Some(value) if value.contains(',') => Ok(Arc::new(ConstantTexture::new(p.vec3(key)?))),
+            Some(_) => self.texture(p, key),
+        }
+    }
+
+    fn material(&self, p: &mut Params) -> Result<SharedMaterial, SceneError> {
+        self.named_material(p, "material")
+    }
+
+    fn named_material(&self, p: &mut Params, key: &str) -> Result<SharedMaterial, SceneError> {
+        let name = p.str(key)?;
+        self.materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| p.error(key, format!("unknown material `{}`", name)))
+    }
+
+    fn instantiate(&self, line: usize, name: &str) -> Option<Result<Box<dyn Hitable>, SceneError>> {
+        match self.objects.get(name) {
+            // Only objects declared above the reference are visible, which rules out cycles.
+            Some(statement) if statement.line < line => Some(self.build_object(statement)),
+            _ => None,
+        }
+    }
+
+    fn child(&self, p: &mut Params, key: &str) -> Result<Box<dyn Hitable>, SceneError> {
+        let name = p.str(key)?;
+        self.instantiate(p.line, name)
+            .unwrap_or_else(|| Err(p.error(key, format!("unknown object `{}`", name))))
+    }
+
+    fn children(&self, p: &mut Params, key: &str) -> Result<Vec<Box<dyn Hitable>>, SceneError> {
+        p.names(key)?
+            .into_iter()
+            .map(|name| {
+                self.instantiate(p.line, name)
+                    .unwrap_or_else(|| Err(p.error(key, format!("unknown object `{}`", name))))
+            })
+            .collect()
+    }
+
+    fn bvh(
+        &self,
+        statement: &Statement,
+        p: &mut Params,
+        objects: Vec<Box<dyn Hitable>>,
+    ) -> Result<Box<dyn Hitable>, SceneError> {
+        if objects.is_empty() {
+            return Err(self.error(p.line, String::from("a bvh needs at least one object")));
+        }
+        let split = match p.get("split") {
+            None => None,
+            Some("median") => Some(SplitMethod::Median),
+            Some("sah") => Some(SplitMethod::Sah),
+            Some(split) => {
+                return Err(p.error(
+                    "split",
+                    format!("expected `median` or `sah`, got `{}`", split),
+                ))
+            }
+        };
+        let layout = match (self.layout, p.get("layout")) {
+            (Some(layout), _) => layout,
+            (None, None) | (None, Some("linear")) => BVHLayout::Linear,
+            (None, Some("boxed")) => BVHLayout::Boxed,
+            (None, Some(layout)) => {
+                return Err(p.error(
+                    "layout",
+                    format!("expected `linear` or `boxed`, got `{}`", layout),
+                ))
+            }
+        };
+        let name = statement.words[0].clone();
+        let (object, stats): (Box<dyn Hitable>, BVHStats) = match (layout, split) {
+            (BVHLayout::Boxed, None) => {
+                let bvh = BVH::new(objects, self.time0, self.time1);
+                let stats = bvh.stats();
+                (Box::new(bvh), stats)
+            }
+            (BVHLayout::Boxed, Some(split)) => {
+                let bvh = BVH::with_split(objects, self.time0, self.time1, split);
+                let stats = bvh.stats();
+                (Box::new(bvh), stats)
+            }
+            (BVHLayout::Linear, split) => {
+                let split = split.unwrap_or(SplitMethod::Median);
+                let bvh = LinearBVH::new(objects, self.time0, self.time1, split);
+                let stats = bvh.stats();
+                (Box::new(bvh), stats)
+            }
+        };
+        self.reports.borrow_mut().push(BVHReport {
+            name,
+            layout,
+            stats,
+        });
+        Ok(object)
+    }
+
+    fn build_object(&self, statement: &Statement) -> Result<Box<dyn Hitable>, SceneError> {
+        let (_, kind) = self.declaration(statement, "object")?;
+        let mut p = self.params(statement);
+        let object: Box<dyn Hitable> = match kind {
+            "sphere" => Box::new(Sphere::new(
+                p.number("radius")?,
+                p.vec3("center")?,
+                self.material(&mut p)?,
+            )),
+            "moving_sphere" => Box::new(MovingSphere::new(
+                p.number("radius")?,
+                p.vec3("center0")?,
+                p.vec3("center1")?,
+                self.material(&mut p)?,
+                p.number_or("time0", 0.0)?,
+                p.number_or("time1", 1.0)?,
+            )),
+            "xy_rect" => Box::new(XYRect::new(
+                p.number("x0")?,
+                p.number("x1")?,
+                p.number("y0")?,
+                p.number("y1")?,
+                p.number("k")?,
+                self.material(&mut p)?,
+            )),
+            "xz_rect" => Box::new(XZRect::new(
+                p.number("x0")?,
+                p.number("x1")?,
+                p.number("z0")?,
+                p.number("z1")?,
+                p.number("k")?,
+                self.material(&mut p)?,
+            )),
+            "yz_rect" => Box::new(YZRect::new(
+                p.number("y0")?,
+                p.number("y1")?,
+                p.number("z0")?,
+                p.number("z1")?,
+                p.number("k")?,
+                self.material(&mut p)?,
+            )),
+            "box" => Box::new(BoxShape::new(
+                p.vec3("min")?,
+                p.vec3("max")?,
+                self.material(&mut p)?,
+            )),
+            "triangle" => {
+                let triangle = Triangle::new(
+                    p.vec3("v0")?,
+                    p.vec3("v1")?,
+                    p.vec3("v2")?,
+                    self.material(&mut p)?,
+                );
+                match triangle {
+                    Some(triangle) => Box::new(triangle),
+                    None => return Err(p.error("v0", String::from("triangle is degenerate"))),
+                }
+            }
+            // A small inline mesh, `positions`, `normals` and `uvs` are flat lists of numbers.
+            "mesh" => {
+                let positions: Vec<Vec3> = p
+                    .numbers::<f32>("positions", 3)?
+                    .chunks(3)
+                    .map(|c| Vec3::new(c[0], c[1], c[2]))
+                    .collect();
+                let indices: Vec<[u32; 3]> = p
+                    .numbers::<u32>("indices", 3)?
+                    .chunks(3)
+                    .map(|c| [c[0], c[1], c[2]])
+                    .collect();
+                if indices
+                    .iter()
+                    .flatten()
+                    .any(|&i| i as usize >= positions.len())
+                {
+                    return Err(p.error("indices", String::from("vertex index out of range")));
+                }
+                let normals: Vec<Vec3> = if p.has("normals") {
+                    p.numbers::<f32>("normals", 3)?
+                        .chunks(3)
+                        .map(|c| Vec3::new(c[0], c[1], c[2]))
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                if !normals.is_empty() && normals.len() != positions.len() {
+                    return Err(
+                        p.error("normals", String::from("expected one normal per position"))
+                    );
+                }
+                let uvs: Vec<(f32, f32)> = if p.has("uvs") {
+                    p.numbers::<f32>("uvs", 2)?
+                        .chunks(2)
+                        .map(|c| (c[0], c[1]))
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                if !uvs.is_empty() && uvs.len() != positions.len() {
+                    return Err(p.error("uvs", String::from("expected one uv per position")));
+                }
+                let material = self.material(&mut p)?;
+                match TriangleMesh::new(positions, normals, uvs, indices, material) {
+                    Ok(mesh) => Box::new(mesh),
+                    Err(message) => return Err(p.error("positions", message)),
+                }
+            }
+            "obj" => {
+                let material = if p.has("material") {
+                    Some(self.material(&mut p)?)
+                } else {
+                    None
+                };
+                Box::new(load_obj(self.resolve(p.str("file")?), material)?)
+            }
+            "ply" => {
+                let file = self.resolve(p.str("file")?);
+                Box::new(load_ply(file, self.material(&mut p)?)?)
+            }
+            "list" => Box::new(HittableList::new(self.children(&mut p, "objects")?)),
+            "bvh" => {
+                let objects = self.children(&mut p, "objects")?;
+                self.bvh(statement, &mut p, objects)?
+            }
+            "flip_normal" => Box::new(FlipNormal::new(self.child(&mut p, "object")?)),
+            "cutout" => {
+                let object = self.child(&mut p, "object")?;
+                let mask = self.texture(&mut p, "mask")?;
+                match p.get("mode").unwrap_or("threshold") {
+                    "threshold" => {
+                        Box::new(Cutout::new(object, mask, p.number_or("threshold", 0.5)?))
+                    }
+                    "stochastic" => Box::new(Cutout::stochastic(object, mask)),
+                    mode => {
+                        return Err(p.error(
+                            "mode",
+                            format!("expected `threshold` or `stochastic`, got `{}`", mode),
+                        ))
+                    }
+                }
+            }
+            "translate" => Box::new(Translate::new(
+                self.child(&mut p, "object")?,
+                p.vec3("offset")?,
+            )),
+            "rotate_y" => Box::new(RotateY::new(
+                self.child(&mut p, "object")?,
+                p.number("angle")?,
+            )),
+            // Operations are applied in the order they are written.
+            "transform" => {
+                let mut transform =
+                    Transform::new(self.child(&mut p, "object")?, Matrix4::identity());
+                for key in p.keys() {
+                    let Some(current) = transform else { break };
+                    transform = match key {
+                        "object" => Some(current),
+                        "translate" => current.translate(p.vec3(key)?),
+                        "scale" => {
+                            let scale = p.vec3(key)?;
+                            if scale.x() * scale.y() * scale.z() == 0.0 {
+                                return Err(p.error(key, String::from("scale must not be zero")));
+                            }
+                            current.scale(scale)
+                        }
+                        "rotate_x" => current.rotate_x(p.number(key)?),
+                        "rotate_y" => current.rotate_y(p.number(key)?),
+                        "rotate_z" => current.rotate_z(p.number(key)?),
+                        "rotate" => {
+                            let [x, y, z, angle] = p.floats::<4>(key)?;
+                            let axis = Vec3::new(x, y, z);
+                            if axis.squared_length() == 0.0 {
+                                return Err(p.error(key, String::from("axis must not be zero")));
+                            }
+                            current.rotate(axis, angle)
+                        }
+                        "look_at" => {
+                            let [fx, fy, fz, tx, ty, tz, ux, uy, uz] = p.floats::<9>(key)?;
+                            let from = Vec3::new(fx, fy, fz);
+                            let to = Vec3::new(tx, ty, tz);
+                            let up = Vec3::new(ux, uy, uz);
+                            if (to - from).squared_length() == 0.0 {
+                                return Err(p.error(key, String::from("`from` and `to` coincide")));
+                            }
+                            if Matrix4::look_at(from, to, up).is_none() {
+                                return Err(p.error(
+                                    key,
+                                    String::from("`up` is zero or parallel to the view direction"),
+                                ));
+                            }
+                            current.look_at(from, to, up)
+                        }
+                        _ => return Err(p.error(key, String::from("unknown transform"))),
+                    };
+                }
+                match transform {
+                    Some(transform) => Box::new(transform),
+                    None => {
+                        return Err(p.error("object", String::from("transform is not invertible")))
+                    }
+                }
+            }
+            "constant_medium" => Box::new(ConstantMedium::new(
+                self.child(&mut p, "object")?,
+                p.number("density")?,
+                self.texture_or_color(&mut p)?,
+            )),
+            // A `count.x` by `count.z` grid of boxes with random heights, e.g. a city floor.
+            "box_grid" => {
+                let mut rng = StdRng::seed_from_u64(p.number_or("seed", 0)?);
+                let origin = p.vec3("origin")?;
+                let size = p.number::<f32>("size")?;
+                let count = p.number::<u32>("count")?;
+                let height_min = p.number::<f32>("height_min")?;
+                let height_max = p.number::<f32>("height_max")?;
+                let material = self.material(&mut p)?;
+                let total = match count.checked_mul(count) {
+                    Some(total) => total as usize,
+                    None => return Err(p.error("count", String::from("too many boxes"))),
+                };
+                let mut boxes: Vec<Box<dyn Hitable>> = Vec::with_capacity(total);
+                for i in 0..count {
+                    for j in 0..count {
+                        let min = origin + Vec3::new(i as f32 * size, 0.0, j as f32 * size);
+                        let height = height_min + (height_max - height_min) * rng.gen::<f32>();
+                        let max = min + Vec3::new(size, height, size);
+                        boxes.push(Box::new(BoxShape::new(min, max, material.clone())));
+                    }
+                }
+                self.bvh(statement, &mut p, boxes)?
+            }
+            // Spheres scattered uniformly inside the box spanned by `min` and `max`.
+            "sphere_cluster" => {
+                let mut rng = StdRng::seed_from_u64(p.number_or("seed", 0)?);
+                let count = p.number::<u32>("count")?;
+                let radius = p.number::<f32>("radius")?;
+                let min = p.vec3("min")?;
+                let max = p.vec3("max")?;
+                let material = self.material(&mut p)?;
+                let spheres = (0..count)
+                    .map(|_| {
+                        let offset = Vec3::new(rng.gen(), rng.gen(), rng.gen()) * (max - min);
+                        Box::new(Sphere::new(radius, min + offset, material.clone()))
+                            as Box<dyn Hitable>
+                    })
+                    .collect();
+                self.bvh(statement, &mut p, spheres)?
+            }
+            _ => return Err(self.error(statement.line, format!("unknown object type `{}`", kind))),
+        };
+        p.finish()?;
+        Ok(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loaders::test_file;
+
+    const HEADER: &str = "camera lookfrom=0,0,-5 lookat=0,0,0 vfov=40
+material white lambertian color=0.73,0.73,0.73
+object ball sphere center=0,0,0 radius=1 material=white
+";
+
+    fn parse_error(name: &str, source: &str) -> (usize, Option<String>, String) {
+        match load_scene(test_file(name, source.as_bytes())) {
+            Err(SceneError::Parse {
+                line, key, message, ..
+            }) => (line, key, message),
+            Err(e) => panic!("expected a parse error, got `{}`", e),
+            Ok(_) => panic!("expected a parse error, the scene loaded"),
+        }
+    }
+
+    fn key(key: &str) -> Option<String> {
+        Some(key.to_string())
+    }
+
+    #[test]
+    fn loads_a_minimal_scene() {
+        let source = format!(
+            "{}light ball\nlight sun directional direction=0,-1,0\n",
+            HEADER
+        );
+        let scene = load_scene(test_file("minimal.scene", source.as_bytes())).unwrap();
+        assert_eq!(scene.objects().entities.len(), 1);
+        assert_eq!(scene.lights().entities.len(), 1);
+        assert_eq!(scene.delta_lights().len(), 1);
+    }
+
+    #[test]
+    fn reports_unknown_statements_and_keys() {
+        let (line, key_name, message) =
+            parse_error("statement.scene", &format!("{}\nsphere ball\n", HEADER));
+        assert_eq!((line, key_name), (5, None));
+        assert_eq!(message, "unknown statement `sphere`");
+
+        let source = format!(
+            "{}material glass dielectric ref_idx=1.5 tint=1,1,1\n",
+            HEADER
+        );
+        let (line, key_name, message) = parse_error("key.scene", &source);
+        assert_eq!((line, key_name), (4, key("tint")));
+        assert_eq!(message, "unknown key");
+    }
+
+    #[test]
+    fn reports_malformed_values() {
+        let source = "camera lookfrom=0,0,-5 lookat=0,0,0 vfov=wide\n";
+        let (line, key_name, message) = parse_error("number.scene", source);
+        assert_eq!((line, key_name), (1, key("vfov")));
+        assert_eq!(message, "expected a number, got `wide`");
+
+        let source = format!(
+            "{}object cube box min=0,0 max=1,1,1 material=white\nadd cube\n",
+            HEADER
+        );
+        let (line, key_name, _) = parse_error("vector.scene", &source);
+        assert_eq!((line, key_name), (4, key("min")));
+
+        let source = format!(
+            "{}object pebble sphere center=0,0,0 radius=1 radius=2 material=white\n",
+            HEADER
+        );
+        let (line, key_name, message) = parse_error("duplicate.scene", &source);
+        assert_eq!((line, key_name), (4, key("radius")));
+        assert_eq!(message, "key given more than once");
+
+        let (line, _, _) = parse_error("order.scene", &format!("{}add x=1 ball\n", HEADER));
+        assert_eq!(line, 4);
+    }
+
+    #[test]
+    fn reports_missing_and_unknown_references() {
+        let source = "material white lambertian color=1,1,1\n";
+        let (line, key_name, message) = parse_error("camera.scene", source);
+        assert_eq!((line, key_name), (0, None));
+        assert_eq!(message, "scene has no `camera` statement");
+
+        let (line, _, message) =
+            parse_error("object.scene", &format!("{}\nadd ball cube\n", HEADER));
+        assert_eq!(line, 5);
+        assert_eq!(message, "unknown object `cube`");
+
+        let source = format!(
+            "{}object cube box min=0,0,0 max=1,1,1 material=black\nadd cube\n",
+            HEADER
+        );
+        let (line, key_name, _) = parse_error("material.scene", &source);
+        assert_eq!((line, key_name), (4, key("material")));
+    }
+
+    #[test]
+    fn reports_degenerate_transforms() {
+        let source = format!(
+            "{}object moved transform object=ball look_at=1,1,1,1,1,1,0,1,0\nadd moved\n",
+            HEADER
+        );
+        let (line, key_name, _) = parse_error("look_at.scene", &source);
+        assert_eq!((line, key_name), (4, key("look_at")));
+
+        let source = format!(
+            "{}object moved transform object=ball translate=nan,0,0\nadd moved\n",
+            HEADER
+        );
+        let (line, key_name, message) = parse_error("transform.scene", &source);
+        assert_eq!((line, key_name), (4, key("object")));
+        assert_eq!(message, "transform is not invertible");
+    }
+
+    #[test]
+    fn rejects_redefinitions() {
+        let source = format!(
+            "{}add ball\nobject ball sphere center=0,0,2 radius=1 material=white\n",
+            HEADER
+        );
+        let (line, key_name, message) = parse_error("object_twice.scene", &source);
+        assert_eq!((line, key_name), (5, None));
+        assert_eq!(message, "object `ball` is already defined on line 3");
+
+        let source = format!("{}render width=10\nrender height=10\n", HEADER);
+        let (line, _, message) = parse_error("render_twice.scene", &source);
+        assert_eq!(line, 5);
+        assert_eq!(message, "`render` is already given on line 4");
+
+        let source = format!("{}camera lookfrom=0,0,5 lookat=0,0,0 vfov=40\n", HEADER);
+        let (line, _, message) = parse_error("camera_twice.scene", &source);
+        assert_eq!(line, 4);
+        assert_eq!(message, "`camera` is already given on line 1");
+    }
+
+    #[test]
+    fn rejects_box_grids_too_large_to_count() {
+        let source = format!(
+            "{}object city box_grid origin=0,0,0 size=1 count=65536 height_min=1 height_max=2 material=white\nadd city\n",
+            HEADER
+        );
+        let (line, key_name, _) = parse_error("box_grid.scene", &source);
+        assert_eq!((line, key_name), (4, key("count")));
+    }
+}
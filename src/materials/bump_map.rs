@@ -0,0 +1,66 @@
+use crate::hittables::HitRecord;
+use crate::math::Vec3;
+use crate::physics::{Material, Ray, ScatterRecord};
+use crate::textures::Texture;
+
+// Step in texture coordinates for the finite differences of the height.
+const DELTA: f32 = 1e-3;
+
+/// Shades `material` as if the surface were displaced along its normal by the red channel of
+/// `height` times `scale`, without moving the geometry.
+///
+/// Any texture works, 3D ones like `NoiseTexture` are looked up at the points the
+/// displacement would be measured at.
+pub struct BumpMap<M: Material, T: Texture> {
+    material: M,
+    height: T,
+    scale: f32,
+}
+
+impl<M: Material, T: Texture> BumpMap<M, T> {
+    pub fn new(material: M, height: T, scale: f32) -> Self {
+        BumpMap {
+            material,
+            height,
+            scale,
+        }
+    }
+
+    fn displacement(&self, u: f32, v: f32, p: &Vec3) -> f32 {
+        self.height.texture(u, v, p).x() * self.scale
+    }
+
+    fn shade<'a>(&self, record: &HitRecord<'a>) -> HitRecord<'a> {
+        let n = record.normal;
+        let (dpdu, dpdv) = record.tangents();
+        let d = self.displacement(record.u, record.v, &record.p);
+        let du = self.displacement(record.u + DELTA, record.v, &(record.p + dpdu * DELTA));
+        let dv = self.displacement(record.u, record.v + DELTA, &(record.p + dpdv * DELTA));
+        let dpdu = dpdu + n * ((du - d) / DELTA);
+        let dpdv = dpdv + n * ((dv - d) / DELTA);
+        let normal = dpdu.cross(dpdv).unit_vector();
+        let mut shaded = *record;
+        shaded.normal = if normal.dot(n) < 0.0 { -normal } else { normal };
+        shaded
+    }
+}
+
+impl<M: Material, T: Texture> Material for BumpMap<M, T> {
+    fn scatter<'a>(&self, r_in: &Ray, record: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>> {
+        let shaded = self.shade(record);
+        let scatter = self.material.scatter(r_in, &shaded)?;
+        Some(scatter.with_record(record))
+    }
+
+    fn emitted(&self, r_in: &Ray, record: &HitRecord) -> Vec3 {
+        self.material.emitted(r_in, record)
+    }
+
+    fn eval(&self, record: &HitRecord, wi: &Vec3, wo: &Vec3) -> Vec3 {
+        self.material.eval(&self.shade(record), wi, wo)
+    }
+
+    fn pdf(&self, record: &HitRecord, wi: &Vec3, wo: &Vec3) -> f32 {
+        self.material.pdf(&self.shade(record), wi, wo)
+    }
+}
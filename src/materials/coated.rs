@@ -0,0 +1,140 @@
+use crate::hittables::HitRecord;
+use crate::materials::microfacet::{fresnel_dielectric, reflect_local, Ggx};
+use crate::math::{Onb, Vec3};
+use crate::physics::{reflect, Material, Ray, ScatterRecord};
+use crate::rand::prelude::*;
+
+/// A dielectric clear coat over any material, smooth or rough like plastic.
+///
+/// The coat reflects by Fresnel and lets the rest through to `base` and back out again.
+/// Refraction inside the coat and reflections between its two interfaces are ignored, so
+/// light reaches the base from the directions it would without the coat. A rough coat is a
+/// GGX reflection lobe, picked as often as the macro surface would reflect.
+pub struct Coated<M: Material> {
+    base: M,
+    ref_idx: f32,
+    ggx: Ggx,
+}
+
+impl<M: Material> Coated<M> {
+    // `roughness` is perceptual like for the other microfacet materials, 0 is the smooth coat.
+    pub fn new(base: M, ref_idx: f32, roughness: f32) -> Self {
+        Coated {
+            base,
+            ref_idx,
+            ggx: Ggx::from_roughness(roughness),
+        }
+    }
+
+    // Reflectance of the coat seen along `w`, from either side of the surface.
+    fn fresnel(&self, record: &HitRecord, w: &Vec3) -> f32 {
+        fresnel_dielectric(record.normal.dot(w.unit_vector()).abs(), self.ref_idx)
+    }
+
+    // The coat is reflective on both sides, so its frame faces the viewer.
+    fn frame(record: &HitRecord, wo: &Vec3) -> Onb {
+        if record.normal.dot(*wo) < 0.0 {
+            Onb::new(-record.normal)
+        } else {
+            Onb::new(record.normal)
+        }
+    }
+
+    // BRDF times cosine and density of the rough coat reflecting `wo` into `wi`, zero for a
+    // smooth coat.
+    fn coat(&self, record: &HitRecord, wi: &Vec3, wo: &Vec3) -> (f32, f32) {
+        if self.ggx.is_smooth() {
+            return (0.0, 0.0);
+        }
+        let wo_world = wo.unit_vector();
+        let frame = Coated::<M>::frame(record, &wo_world);
+        let wo = frame.world_to_local(&wo_world);
+        let wi = frame.world_to_local(&wi.unit_vector());
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let m = (wo + wi).unit_vector();
+        let fresnel = fresnel_dielectric(wo.dot(m), self.ref_idx);
+        (
+            fresnel * self.ggx.d(&m) * self.ggx.g(&wo, &wi) / (4.0 * wo.z()),
+            self.ggx.visible_pdf(&wo, &m) / (4.0 * wo.dot(m)),
+        )
+    }
+}
+
+impl<M: Material> Material for Coated<M> {
+    fn scatter<'a>(&self, r_in: &Ray, record: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>> {
+        let wo = -r_in.direction;
+        let reflectance = self.fresnel(record, &wo);
+        if rand::thread_rng().gen::<f32>() < reflectance {
+            if self.ggx.is_smooth() {
+                return Some(ScatterRecord::specular(
+                    record,
+                    Vec3::new(1.0, 1.0, 1.0),
+                    reflect(&r_in.direction.unit_vector(), &record.normal),
+                ));
+            }
+            let frame = Coated::<M>::frame(record, &wo);
+            let wo_local = frame.world_to_local(&wo.unit_vector());
+            let wi = reflect_local(&wo_local, &self.ggx.sample_visible(&wo_local));
+            if wi.z() <= 0.0 {
+                return None;
+            }
+            let direction = frame.local_to_world(&wi);
+            let pdf = self.pdf(record, &direction, &wo);
+            if pdf <= 0.0 {
+                return None;
+            }
+            return Some(ScatterRecord::new(
+                record,
+                self.eval(record, &direction, &wo) / pdf,
+                direction,
+                pdf,
+            ));
+        }
+        let mut scatter = self.base.scatter(r_in, record)?;
+        if scatter.specular {
+            scatter.attenuation *= 1.0 - self.fresnel(record, &scatter.direction);
+        } else {
+            let (_, coat_pdf) = self.coat(record, &scatter.direction, &wo);
+            scatter.pdf = (1.0 - reflectance) * scatter.pdf + reflectance * coat_pdf;
+            scatter.attenuation = self.eval(record, &scatter.direction, &wo) / scatter.pdf;
+        }
+        Some(scatter)
+    }
+
+    fn emitted(&self, r_in: &Ray, record: &HitRecord) -> Vec3 {
+        self.base.emitted(r_in, record)
+    }
+
+    fn eval(&self, record: &HitRecord, wi: &Vec3, wo: &Vec3) -> Vec3 {
+        let transmitted = (1.0 - self.fresnel(record, wo)) * (1.0 - self.fresnel(record, wi));
+        let (coat, _) = self.coat(record, wi, wo);
+        self.base.eval(record, wi, wo) * transmitted + Vec3::new(coat, coat, coat)
+    }
+
+    fn pdf(&self, record: &HitRecord, wi: &Vec3, wo: &Vec3) -> f32 {
+        let reflectance = self.fresnel(record, wo);
+        let (_, coat_pdf) = self.coat(record, wi, wo);
+        (1.0 - reflectance) * self.base.pdf(record, wi, wo) + reflectance * coat_pdf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::{assert_consistent_sampling, Conductor, Lambertian};
+    use crate::textures::ConstantTexture;
+
+    fn grey(value: f32) -> ConstantTexture {
+        ConstantTexture::new(Vec3::new(value, value, value))
+    }
+
+    #[test]
+    fn samples_agree_with_eval_and_pdf() {
+        for roughness in [0.0, 0.3, 1.0] {
+            assert_consistent_sampling(&Coated::new(Lambertian::new(grey(0.8)), 1.5, roughness));
+            assert_consistent_sampling(&Coated::new(Conductor::gold(grey(0.5)), 1.5, roughness));
+        }
+    }
+}
@@ -1,38 +1,64 @@
 use crate::hittables::HitRecord;
 use crate::math::{dot, Vec3};
-use crate::physics::{reflect, refract, schlick, Material, Ray};
+use crate::physics::{reflect, refract, schlick, Material, Ray, ScatterRecord};
 use crate::rand::prelude::*;
 
+/// Smooth glass. Light travelling inside is absorbed following Beer–Lambert, which assumes the
+/// object is closed and the ray entered through its own surface.
 #[derive(Copy, Clone)]
 pub struct Dielectric {
     ref_idx: f32,
+    // Absorption coefficient per unit length, zero for clear glass.
+    absorption: Vec3,
 }
 
 impl Dielectric {
     pub fn new(ref_idx: f32) -> Self {
-        Dielectric { ref_idx }
+        Dielectric {
+            ref_idx,
+            absorption: Vec3::default(),
+        }
+    }
+
+    /// Tinted glass that lets `transmittance` of the light through after `distance`.
+    pub fn with_absorption(ref_idx: f32, transmittance: Vec3, distance: f32) -> Self {
+        debug_assert!(distance > 0.0, "absorption distance must be positive");
+        let coefficient = |t: f32| -t.clamp(1e-6, 1.0).ln() / distance;
+        Dielectric {
+            ref_idx,
+            absorption: Vec3::new(
+                coefficient(transmittance.x()),
+                coefficient(transmittance.y()),
+                coefficient(transmittance.z()),
+            ),
+        }
+    }
+
+    // Attenuation along the segment inside from where the ray entered to `record`.
+    fn transmittance(&self, r: &Ray, record: &HitRecord) -> Vec3 {
+        let distance = record.t * r.direction.length();
+        Vec3::new(
+            (-self.absorption.x() * distance).exp(),
+            (-self.absorption.y() * distance).exp(),
+            (-self.absorption.z() * distance).exp(),
+        )
     }
 }
 
 impl Material for Dielectric {
-    fn scatter(
-        &self,
-        r: &Ray,
-        record: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
+    fn scatter<'a>(&self, r: &Ray, record: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>> {
         let mut rng = rand::thread_rng();
         let outward_normal: Vec3;
         let ni_over_nt: f32;
         let reflected = reflect(&r.direction, &record.normal);
         let cosine: f32;
-        *attenuation = Vec3::new(1.0, 1.0, 1.0);
+        let mut attenuation = Vec3::new(1.0, 1.0, 1.0);
 
         if dot(&r.direction, &record.normal) > 0.0 {
             outward_normal = -record.normal;
             ni_over_nt = self.ref_idx;
             cosine = self.ref_idx * dot(&r.direction, &record.normal) / r.direction.length();
+            attenuation = self.transmittance(r, record);
         } else {
             outward_normal = record.normal;
             ni_over_nt = 1.0 / self.ref_idx;
@@ -47,11 +73,11 @@ impl Material for Dielectric {
         };
 
         let chance: f32 = rng.gen();
-        if chance < reflect_prob {
-            *scattered = Ray::new(record.p, reflected, r.time);
+        let direction = if chance < reflect_prob {
+            reflected
         } else {
-            *scattered = Ray::new(record.p, refracted.unwrap(), r.time);
-        }
-        true
+            refracted.unwrap()
+        };
+        Some(ScatterRecord::specular(record, attenuation, direction))
     }
 }
@@ -1,29 +1,39 @@
 use crate::hittables::HitRecord;
 use crate::math::Vec3;
-use crate::physics::{Material, Ray};
+use crate::physics::{Material, Ray, ScatterRecord};
 use crate::textures::Texture;
 
+/// Emits the colour of `texture` times `intensity`. Two-sided lights shine from both faces,
+/// one-sided ones only to the side their normal points to.
 pub struct DiffuseLight<T: Texture> {
     emit: T,
+    intensity: f32,
+    two_sided: bool,
 }
 
 impl<T: Texture> DiffuseLight<T> {
     pub fn new(texture: T) -> Self {
-        DiffuseLight { emit: texture }
+        DiffuseLight::with_options(texture, 1.0, true)
+    }
+
+    pub fn with_options(texture: T, intensity: f32, two_sided: bool) -> Self {
+        DiffuseLight {
+            emit: texture,
+            intensity,
+            two_sided,
+        }
     }
 }
 
 impl<T: Texture> Material for DiffuseLight<T> {
-    fn scatter(
-        &self,
-        _r: &Ray,
-        _record: &HitRecord,
-        _attenuation: &mut Vec3,
-        _scattered: &mut Ray,
-    ) -> bool {
-        false
+    fn scatter<'a>(&self, _r: &Ray, _record: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>> {
+        None
     }
-    fn emitted(&self, u: f32, v: f32, p: &Vec3) -> Vec3 {
-        self.emit.texture(u, v, p)
+
+    fn emitted(&self, r_in: &Ray, record: &HitRecord) -> Vec3 {
+        if !self.two_sided && r_in.direction.dot(record.normal) > 0.0 {
+            return Vec3::default();
+        }
+        self.emit.texture(record.u, record.v, &record.p) * self.intensity
     }
 }
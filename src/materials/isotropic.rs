@@ -1,8 +1,9 @@
 use crate::hittables::HitRecord;
+use crate::math::sampling::{uniform_sphere, uniform_sphere_pdf};
 use crate::math::Vec3;
-use crate::physics::{Material, Ray};
-use crate::random_in_unit_sphere;
+use crate::physics::{Material, Ray, ScatterRecord};
 use crate::textures::Texture;
+use std::f32::consts::PI;
 
 pub struct Isotropic<T: Texture> {
     albedo: T,
@@ -15,15 +16,20 @@ impl<T: Texture> Isotropic<T> {
 }
 
 impl<T: Texture> Material for Isotropic<T> {
-    fn scatter(
-        &self,
-        r: &Ray,
-        record: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
-        *scattered = Ray::new(record.p, random_in_unit_sphere(), r.time);
-        *attenuation = self.albedo.texture(record.u, record.v, &record.p);
-        true
+    fn scatter<'a>(&self, _r: &Ray, record: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>> {
+        Some(ScatterRecord::new(
+            record,
+            self.albedo.texture(record.u, record.v, &record.p),
+            uniform_sphere(),
+            uniform_sphere_pdf(),
+        ))
+    }
+
+    fn eval(&self, record: &HitRecord, _wi: &Vec3, _wo: &Vec3) -> Vec3 {
+        self.albedo.texture(record.u, record.v, &record.p) / (4.0 * PI)
+    }
+
+    fn pdf(&self, _record: &HitRecord, _wi: &Vec3, _wo: &Vec3) -> f32 {
+        uniform_sphere_pdf()
     }
 }
@@ -1,8 +1,9 @@
 use crate::hittables::HitRecord;
-use crate::math::Vec3;
-use crate::physics::{Material, Ray};
-use crate::random_in_unit_sphere;
+use crate::math::sampling::{cosine_hemisphere, cosine_hemisphere_pdf};
+use crate::math::{Onb, Vec3};
+use crate::physics::{Material, Ray, ScatterRecord};
 use crate::textures::Texture;
+use std::f32::consts::PI;
 
 #[derive(Clone)]
 pub struct Lambertian<T: Texture + Clone> {
@@ -16,16 +17,22 @@ impl<T: Texture + Clone> Lambertian<T> {
 }
 
 impl<T: Texture + Clone> Material for Lambertian<T> {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
-        let target = rec.p + rec.normal + random_in_unit_sphere();
-        *scattered = Ray::new(rec.p, target - rec.p, r_in.time);
-        *attenuation = self.albedo.texture(rec.u, rec.v, &rec.p);
-        true
+    fn scatter<'a>(&self, _r_in: &Ray, rec: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>> {
+        let local = cosine_hemisphere();
+        Some(ScatterRecord::new(
+            rec,
+            self.albedo.texture(rec.u, rec.v, &rec.p),
+            Onb::new(rec.normal).local_to_world(&local),
+            cosine_hemisphere_pdf(local.z()),
+        ))
+    }
+
+    fn eval(&self, rec: &HitRecord, wi: &Vec3, _wo: &Vec3) -> Vec3 {
+        let cosine = rec.normal.dot(wi.unit_vector()).max(0.0);
+        self.albedo.texture(rec.u, rec.v, &rec.p) * cosine / PI
+    }
+
+    fn pdf(&self, rec: &HitRecord, wi: &Vec3, _wo: &Vec3) -> f32 {
+        cosine_hemisphere_pdf(rec.normal.dot(wi.unit_vector()))
     }
 }
@@ -1,7 +1,8 @@
 use crate::hittables::HitRecord;
+use crate::math::sampling::random_in_unit_sphere;
 use crate::math::{dot, Vec3};
-use crate::physics::{reflect, Material, Ray};
-use crate::random_in_unit_sphere;
+use crate::physics::{reflect, Material, Ray, ScatterRecord};
+use std::f32::consts::PI;
 
 pub struct Metal {
     albedo: Vec3,
@@ -18,20 +19,47 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
+    fn scatter<'a>(&self, r_in: &Ray, rec: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>> {
         let reflected = reflect(&r_in.direction.unit_vector(), &rec.normal);
-        *scattered = Ray::new(
-            rec.p,
-            reflected + self.fuzz * random_in_unit_sphere(),
-            r_in.time,
-        );
-        *attenuation = self.albedo;
-        dot(&scattered.direction, &rec.normal) > 0.0
+        let direction = reflected + self.fuzz * random_in_unit_sphere();
+        if dot(&direction, &rec.normal) <= 0.0 {
+            return None;
+        }
+        if self.fuzz <= 0.0 {
+            return Some(ScatterRecord::specular(rec, self.albedo, direction));
+        }
+        let wo = -r_in.direction;
+        Some(ScatterRecord::new(
+            rec,
+            self.albedo,
+            direction,
+            self.pdf(rec, &direction, &wo),
+        ))
+    }
+
+    // The lobe's BSDF times cosine is the albedo scaled by the sampling density.
+    fn eval(&self, rec: &HitRecord, wi: &Vec3, wo: &Vec3) -> Vec3 {
+        self.albedo * self.pdf(rec, wi, wo)
+    }
+
+    // Directions are the reflection plus a point uniform in a ball of radius `fuzz`, so the
+    // density along a direction is the ball volume it crosses, seen from the origin.
+    fn pdf(&self, rec: &HitRecord, wi: &Vec3, wo: &Vec3) -> f32 {
+        let direction = wi.unit_vector();
+        if self.fuzz <= 0.0 || dot(&direction, &rec.normal) <= 0.0 {
+            return 0.0;
+        }
+        let reflected = reflect(&-wo.unit_vector(), &rec.normal);
+        let c = dot(&direction, &reflected);
+        let discriminant = c * c - 1.0 + self.fuzz * self.fuzz;
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+        let t0 = (c - discriminant.sqrt()).max(0.0);
+        let t1 = c + discriminant.sqrt();
+        if t1 <= 0.0 {
+            return 0.0;
+        }
+        (t1.powi(3) - t0.powi(3)) / (4.0 * PI * self.fuzz.powi(3))
     }
 }
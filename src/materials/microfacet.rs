@@ -0,0 +1,176 @@
+// Trowbridge-Reitz (GGX) microfacet distribution and the Fresnel terms used with it.
+//
+// Everything works in a shading frame with the macro normal along +z. Masking and shadowing
+// use the height-correlated Smith form.
+
+use crate::math::Vec3;
+use crate::rand::prelude::*;
+use std::f32::consts::PI;
+
+// Below this alpha a lobe is treated as a perfect mirror, the distribution overflows.
+pub(crate) const MIN_ALPHA: f32 = 1e-3;
+
+#[derive(Copy, Clone)]
+pub(crate) struct Ggx {
+    alpha: f32,
+}
+
+impl Ggx {
+    // Perceptual roughness in [0, 1], squared as usual so it reads linearly.
+    pub(crate) fn from_roughness(roughness: f32) -> Self {
+        let roughness = roughness.clamp(0.0, 1.0);
+        Ggx {
+            alpha: roughness * roughness,
+        }
+    }
+
+    pub(crate) fn is_smooth(&self) -> bool {
+        self.alpha < MIN_ALPHA
+    }
+
+    // Density of micro normals `m` per unit projected area.
+    pub(crate) fn d(&self, m: &Vec3) -> f32 {
+        if m.z() <= 0.0 {
+            return 0.0;
+        }
+        let a2 = self.alpha * self.alpha;
+        let t = m.z() * m.z() * (a2 - 1.0) + 1.0;
+        a2 / (PI * t * t)
+    }
+
+    fn lambda(&self, w: &Vec3) -> f32 {
+        let cos2 = w.z() * w.z();
+        if cos2 <= 0.0 {
+            return 0.0;
+        }
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+        ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0) / 2.0
+    }
+
+    pub(crate) fn g1(&self, w: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    pub(crate) fn g(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // Micro normal density as seen from `wo`, the distribution `sample_visible` draws from.
+    pub(crate) fn visible_pdf(&self, wo: &Vec3, m: &Vec3) -> f32 {
+        if wo.z() == 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * wo.dot(*m).max(0.0) * self.d(m) / wo.z().abs()
+    }
+
+    // Heitz 2018, "Sampling the GGX Distribution of Visible Normals". `wo` must be in the
+    // upper hemisphere.
+    pub(crate) fn sample_visible(&self, wo: &Vec3) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let vh = Vec3::new(self.alpha * wo.x(), self.alpha * wo.y(), wo.z()).unit_vector();
+        let len2 = vh.x() * vh.x() + vh.y() * vh.y();
+        let t1 = if len2 > 0.0 {
+            Vec3::new(-vh.y(), vh.x(), 0.0) / len2.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(t1);
+        let r = rng.gen::<f32>().sqrt();
+        let phi = 2.0 * PI * rng.gen::<f32>();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z());
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+        Vec3::new(self.alpha * nh.x(), self.alpha * nh.y(), nh.z().max(0.0)).unit_vector()
+    }
+}
+
+pub(crate) fn reflect_local(wo: &Vec3, m: &Vec3) -> Vec3 {
+    2.0 * wo.dot(*m) * *m - *wo
+}
+
+// Unpolarised reflectance of a conductor with complex index `eta + i k` relative to the
+// outside medium, for the cosine between the incident direction and the micro normal.
+pub(crate) fn fresnel_conductor(cos_i: f32, eta: f32, k: f32) -> f32 {
+    let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i.max(0.0) * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    (rs + rp) / 2.0
+}
+
+pub(crate) fn fresnel_conductor_rgb(cos_i: f32, eta: &Vec3, k: &Vec3) -> Vec3 {
+    Vec3::new(
+        fresnel_conductor(cos_i, eta.x(), k.x()),
+        fresnel_conductor(cos_i, eta.y(), k.y()),
+        fresnel_conductor(cos_i, eta.z(), k.z()),
+    )
+}
+
+// Refracts `wo` through the micro normal `m` into a medium `eta` times as dense as the one
+// `wo` lies in. None on total internal reflection.
+pub(crate) fn refract_local(wo: &Vec3, m: &Vec3, eta: f32) -> Option<Vec3> {
+    let cos_i = wo.dot(*m);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-*wo / eta + (cos_i / eta - cos_t) * *m)
+}
+
+// Exact unpolarised reflectance of a dielectric boundary, `eta` as in `refract_local`.
+pub(crate) fn fresnel_dielectric(cos_i: f32, eta: f32) -> f32 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+// Micro normal between `wo` and `wi` facing `wo`, and whether `wi` is transmitted. None when
+// no micro normal links the two directions.
+pub(crate) fn generalized_half_vector(wo: &Vec3, wi: &Vec3, eta: f32) -> Option<(Vec3, bool)> {
+    let transmitted = wi.z() < 0.0;
+    let m = if transmitted {
+        *wo + *wi * eta
+    } else {
+        *wo + *wi
+    };
+    if m.squared_length() == 0.0 {
+        return None;
+    }
+    let m = if m.z() < 0.0 { -m } else { m }.unit_vector();
+    let wrong_side = if transmitted {
+        wi.dot(m) >= 0.0
+    } else {
+        wi.dot(m) <= 0.0
+    };
+    if wo.dot(m) <= 0.0 || wrong_side {
+        return None;
+    }
+    Some((m, transmitted))
+}
+
+// Change of variables from micro normals to transmitted directions.
+pub(crate) fn refraction_jacobian(wo: &Vec3, wi: &Vec3, m: &Vec3, eta: f32) -> f32 {
+    let denom = wi.dot(*m) + wo.dot(*m) / eta;
+    wi.dot(*m).abs() / (denom * denom)
+}
+
+pub(crate) fn schlick_weight(cos: f32) -> f32 {
+    (1.0 - cos.clamp(0.0, 1.0)).powi(5)
+}
@@ -0,0 +1,92 @@
+use crate::hittables::HitRecord;
+use crate::math::Vec3;
+use crate::physics::{Material, Ray, ScatterRecord};
+use crate::rand::prelude::*;
+use crate::textures::Texture;
+
+/// Blends two materials by a mask, `a` where its red channel is 0 and `b` where it is 1.
+pub struct MixMaterial<A: Material, B: Material, T: Texture> {
+    a: A,
+    b: B,
+    mask: T,
+}
+
+impl<A: Material, B: Material, T: Texture> MixMaterial<A, B, T> {
+    pub fn new(a: A, b: B, mask: T) -> Self {
+        MixMaterial { a, b, mask }
+    }
+
+    fn weight(&self, u: f32, v: f32, p: &Vec3) -> f32 {
+        self.mask.texture(u, v, p).x().clamp(0.0, 1.0)
+    }
+}
+
+impl<A: Material, B: Material, T: Texture> Material for MixMaterial<A, B, T> {
+    // Scatters with one of the two picked by the mask. Specular samples keep their weight,
+    // the others are weighted against the blend of both lobes.
+    fn scatter<'a>(&self, r_in: &Ray, record: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>> {
+        let w = self.weight(record.u, record.v, &record.p);
+        let scatter = if rand::thread_rng().gen::<f32>() < w {
+            self.b.scatter(r_in, record)?
+        } else {
+            self.a.scatter(r_in, record)?
+        };
+        if scatter.specular {
+            return Some(scatter);
+        }
+        let wo = -r_in.direction;
+        let pdf = self.pdf(record, &scatter.direction, &wo);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let eval = self.eval(record, &scatter.direction, &wo);
+        Some(ScatterRecord::new(
+            record,
+            eval / pdf,
+            scatter.direction,
+            pdf,
+        ))
+    }
+
+    fn emitted(&self, r_in: &Ray, record: &HitRecord) -> Vec3 {
+        let w = self.weight(record.u, record.v, &record.p);
+        self.a.emitted(r_in, record) * (1.0 - w) + self.b.emitted(r_in, record) * w
+    }
+
+    fn eval(&self, record: &HitRecord, wi: &Vec3, wo: &Vec3) -> Vec3 {
+        let w = self.weight(record.u, record.v, &record.p);
+        self.a.eval(record, wi, wo) * (1.0 - w) + self.b.eval(record, wi, wo) * w
+    }
+
+    fn pdf(&self, record: &HitRecord, wi: &Vec3, wo: &Vec3) -> f32 {
+        let w = self.weight(record.u, record.v, &record.p);
+        self.a.pdf(record, wi, wo) * (1.0 - w) + self.b.pdf(record, wi, wo) * w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::{assert_consistent_sampling, Conductor, Dielectric, Lambertian};
+    use crate::textures::ConstantTexture;
+
+    fn grey(value: f32) -> ConstantTexture {
+        ConstantTexture::new(Vec3::new(value, value, value))
+    }
+
+    #[test]
+    fn samples_agree_with_eval_and_pdf() {
+        for mask in [0.0, 0.3, 1.0] {
+            assert_consistent_sampling(&MixMaterial::new(
+                Lambertian::new(grey(0.8)),
+                Conductor::copper(grey(0.4)),
+                grey(mask),
+            ));
+        }
+        assert_consistent_sampling(&MixMaterial::new(
+            Lambertian::new(grey(0.8)),
+            Dielectric::new(1.5),
+            grey(0.5),
+        ));
+    }
+}
@@ -1,7 +1,83 @@
+mod bump_map;
+mod coated;
+mod conductor;
 mod dielectric;
 mod diffuse_light;
 mod isotropic;
 mod lambertian;
 mod metal;
+mod microfacet;
+mod mix;
+mod normal_map;
+mod principled;
+mod rough_dielectric;
 
-pub use self::{dielectric::*, diffuse_light::*, isotropic::*, lambertian::*, metal::*};
+pub use self::{
+    bump_map::*, coated::*, conductor::*, dielectric::*, diffuse_light::*, isotropic::*,
+    lambertian::*, metal::*, mix::*, normal_map::*, principled::*, rough_dielectric::*,
+};
+
+// Scatters off a flat surface facing +z from several sides and checks that every sampled,
+// non-specular direction agrees with `eval` and `pdf`, and that no more light leaves than
+// arrives on average.
+#[cfg(test)]
+pub(crate) fn assert_consistent_sampling(material: &dyn crate::physics::Material) {
+    use crate::hittables::HitRecord;
+    use crate::math::Vec3;
+    use crate::physics::Ray;
+
+    // Loose enough for f32 half vectors of near-mirror lobes, whose densities run into the
+    // hundreds.
+    let close = |a: f32, b: f32| (a - b).abs() <= 1e-2 * a.abs().max(b.abs()) + 1e-4;
+    let record = HitRecord::new(
+        1.0,
+        Vec3::default(),
+        Vec3::new(0.0, 0.0, 1.0),
+        material,
+        0.3,
+        0.7,
+    )
+    .with_tangents(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    for cos in [0.95, 0.6, 0.2, -0.5] {
+        let wo = Vec3::new((1.0f32 - cos * cos).sqrt(), 0.0, cos);
+        let r_in = Ray::new(wo, -wo, 0.0);
+        let samples = 20_000;
+        let mut albedo = Vec3::default();
+        for _ in 0..samples {
+            let scatter = match material.scatter(&r_in, &record) {
+                Some(scatter) => scatter,
+                None => continue,
+            };
+            albedo += scatter.attenuation;
+            if scatter.specular {
+                continue;
+            }
+            let wi = scatter.direction;
+            let pdf = material.pdf(&record, &wi, &wo);
+            assert!(
+                close(scatter.pdf, pdf),
+                "pdf {} vs {} for {:?}",
+                scatter.pdf,
+                pdf,
+                wi
+            );
+            let expected = material.eval(&record, &wi, &wo) / scatter.pdf;
+            for c in 0..3 {
+                assert!(
+                    close(scatter.attenuation[c], expected[c]),
+                    "attenuation {:?} vs eval / pdf {:?} for {:?}",
+                    scatter.attenuation,
+                    expected,
+                    wi
+                );
+            }
+        }
+        albedo /= samples as f32;
+        assert!(
+            albedo.max_component() <= 1.02,
+            "albedo {:?} at cos {}",
+            albedo,
+            cos
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use crate::hittables::HitRecord;
+use crate::math::Vec3;
+use crate::physics::{Material, Ray, ScatterRecord};
+use crate::textures::Texture;
+
+/// Shades `material` with normals from a tangent space normal map, typically an
+/// `ImageTexture` with the usual `0.5 + n / 2` encoding and blue pointing out of the surface.
+///
+/// The tangent frame follows `dpdu` and `dpdv` of the hit, so the map is oriented by the
+/// texture coordinates.
+pub struct NormalMap<M: Material, T: Texture> {
+    material: M,
+    normals: T,
+}
+
+impl<M: Material, T: Texture> NormalMap<M, T> {
+    pub fn new(material: M, normals: T) -> Self {
+        NormalMap { material, normals }
+    }
+
+    fn shade<'a>(&self, record: &HitRecord<'a>) -> HitRecord<'a> {
+        let n = record.normal;
+        let (dpdu, dpdv) = record.tangents();
+        let tangent = (dpdu - n * n.dot(dpdu)).unit_vector();
+        let bitangent = dpdv - n * n.dot(dpdv) - tangent * tangent.dot(dpdv);
+        let bitangent = if bitangent.squared_length() > 0.0 {
+            bitangent.unit_vector()
+        } else {
+            n.cross(tangent)
+        };
+        let c =
+            self.normals.texture(record.u, record.v, &record.p) * 2.0 - Vec3::new(1.0, 1.0, 1.0);
+        let mut shaded = *record;
+        let normal = (tangent * c.x() + bitangent * c.y() + n * c.z()).unit_vector();
+        shaded.normal = if normal.dot(n) < 0.0 { -normal } else { normal };
+        shaded
+    }
+}
+
+impl<M: Material, T: Texture> Material for NormalMap<M, T> {
+    fn scatter<'a>(&self, r_in: &Ray, record: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>> {
+        let shaded = self.shade(record);
+        let scatter = self.material.scatter(r_in, &shaded)?;
+        Some(scatter.with_record(record))
+    }
+
+    fn emitted(&self, r_in: &Ray, record: &HitRecord) -> Vec3 {
+        self.material.emitted(r_in, record)
+    }
+
+    fn eval(&self, record: &HitRecord, wi: &Vec3, wo: &Vec3) -> Vec3 {
+        self.material.eval(&self.shade(record), wi, wo)
+    }
+
+    fn pdf(&self, record: &HitRecord, wi: &Vec3, wo: &Vec3) -> f32 {
+        self.material.pdf(&self.shade(record), wi, wo)
+    }
+}
@@ -0,0 +1,312 @@
+use crate::hittables::HitRecord;
+use crate::materials::microfacet::{
+    fresnel_dielectric, generalized_half_vector, reflect_local, refract_local, refraction_jacobian,
+    schlick_weight, Ggx,
+};
+use crate::math::sampling::{cosine_hemisphere, cosine_hemisphere_pdf};
+use crate::math::{Onb, Vec3};
+use crate::physics::{Material, Ray, ScatterRecord};
+use crate::rand::prelude::*;
+use crate::textures::{ConstantTexture, Texture};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+// Lower bound on roughness so every lobe stays a distribution light sampling can hit.
+const MIN_ROUGHNESS: f32 = 0.05;
+const CLEARCOAT_ROUGHNESS: f32 = 0.1;
+const CLEARCOAT_IOR: f32 = 1.5;
+
+/// One material for most surfaces, after the Disney principled BRDF (Burley 2012) with an
+/// added rough transmission lobe.
+///
+/// A diffuse base with sheen, a GGX specular lobe and a clearcoat layer on top. `metallic`
+/// turns the base into a conductor tinted by `base_color`, `transmission` into rough glass.
+/// `specular` sets the reflectance of the dielectric base, 0.5 is an index of 1.5. Every
+/// parameter is a texture, the scalar ones are read from the red channel.
+pub struct Principled {
+    base_color: Arc<dyn Texture + Send>,
+    metallic: Arc<dyn Texture + Send>,
+    roughness: Arc<dyn Texture + Send>,
+    specular: Arc<dyn Texture + Send>,
+    clearcoat: Arc<dyn Texture + Send>,
+    sheen: Arc<dyn Texture + Send>,
+    transmission: Arc<dyn Texture + Send>,
+    emission: Arc<dyn Texture + Send>,
+}
+
+// Parameters looked up at one hit, with `ior` the index of the outside and `eta` the relative
+// index across the surface, which is inverted from the inside.
+struct Surface {
+    base_color: Vec3,
+    metallic: f32,
+    specular: Ggx,
+    clearcoat: f32,
+    sheen: f32,
+    transmission: f32,
+    ior: f32,
+    eta: f32,
+}
+
+impl Surface {
+    // Probability of sampling the diffuse, specular, clearcoat and transmission lobes.
+    fn lobes(&self) -> [f32; 4] {
+        let weights = [
+            (1.0 - self.metallic) * (1.0 - self.transmission),
+            1.0,
+            0.5 * self.clearcoat,
+            (1.0 - self.metallic) * self.transmission,
+        ];
+        let total: f32 = weights.iter().sum();
+        weights.map(|w| w / total)
+    }
+
+    fn fresnel(&self, cos: f32) -> Vec3 {
+        // Opaque surfaces reflect the same from both sides, only the transmissive part sees the
+        // inside of an object.
+        let dielectric = (1.0 - self.transmission) * fresnel_dielectric(cos, self.ior)
+            + self.transmission * fresnel_dielectric(cos, self.eta);
+        let dielectric = Vec3::new(dielectric, dielectric, dielectric);
+        let weight = schlick_weight(cos);
+        let conductor = self.base_color + (Vec3::new(1.0, 1.0, 1.0) - self.base_color) * weight;
+        dielectric * (1.0 - self.metallic) + conductor * self.metallic
+    }
+
+    // Fraction of the light the clearcoat lets through to the layers below along `w`.
+    fn under_clearcoat(&self, w: &Vec3) -> f32 {
+        1.0 - self.clearcoat * fresnel_dielectric(w.z().abs(), CLEARCOAT_IOR)
+    }
+
+    // BSDF times cosine with both directions in the shading frame, `wo` above the surface.
+    fn eval(&self, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        if wo.z() <= 0.0 {
+            return Vec3::default();
+        }
+        if wi.z() > 0.0 {
+            let h = (*wo + *wi).unit_vector();
+            let diffuse =
+                self.base_color * ((1.0 - self.metallic) * (1.0 - self.transmission) * wi.z() / PI);
+            let sheen = (1.0 - self.metallic) * self.sheen * schlick_weight(wi.dot(h)) * wi.z();
+            let specular = self.fresnel(wo.dot(h))
+                * (self.specular.d(&h) * self.specular.g(wo, wi) / (4.0 * wo.z()));
+            let coat = Ggx::from_roughness(CLEARCOAT_ROUGHNESS);
+            let clearcoat = self.clearcoat
+                * fresnel_dielectric(wo.dot(h), CLEARCOAT_IOR)
+                * coat.d(&h)
+                * coat.g(wo, wi)
+                / (4.0 * wo.z());
+            let base = (diffuse + specular + Vec3::new(sheen, sheen, sheen))
+                * (self.under_clearcoat(wo) * self.under_clearcoat(wi));
+            base + Vec3::new(clearcoat, clearcoat, clearcoat)
+        } else {
+            let weight = (1.0 - self.metallic) * self.transmission;
+            let m = match generalized_half_vector(wo, wi, self.eta) {
+                Some((m, true)) if weight > 0.0 => m,
+                _ => return Vec3::default(),
+            };
+            let fresnel = fresnel_dielectric(wo.dot(m), self.eta);
+            let jacobian = refraction_jacobian(wo, wi, &m, self.eta);
+            self.base_color
+                * (weight
+                    * self.under_clearcoat(wo)
+                    * (1.0 - fresnel)
+                    * self.specular.d(&m)
+                    * self.specular.g(wo, wi)
+                    * wo.dot(m)
+                    * jacobian
+                    / wo.z())
+        }
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        if wo.z() <= 0.0 {
+            return 0.0;
+        }
+        let [diffuse, specular, clearcoat, transmission] = self.lobes();
+        if wi.z() > 0.0 {
+            let h = (*wo + *wi).unit_vector();
+            let coat = Ggx::from_roughness(CLEARCOAT_ROUGHNESS);
+            diffuse * cosine_hemisphere_pdf(wi.z())
+                + (specular * self.specular.visible_pdf(wo, &h)
+                    + clearcoat * coat.visible_pdf(wo, &h))
+                    / (4.0 * wo.dot(h))
+        } else {
+            match generalized_half_vector(wo, wi, self.eta) {
+                Some((m, true)) if transmission > 0.0 => {
+                    transmission
+                        * self.specular.visible_pdf(wo, &m)
+                        * refraction_jacobian(wo, wi, &m, self.eta)
+                }
+                _ => 0.0,
+            }
+        }
+    }
+
+    fn sample(&self, wo: &Vec3) -> Option<Vec3> {
+        let [diffuse, specular, clearcoat, _] = self.lobes();
+        let choice = rand::thread_rng().gen::<f32>();
+        if choice < diffuse {
+            Some(cosine_hemisphere())
+        } else if choice < diffuse + specular {
+            Some(reflect_local(wo, &self.specular.sample_visible(wo)))
+        } else if choice < diffuse + specular + clearcoat {
+            let coat = Ggx::from_roughness(CLEARCOAT_ROUGHNESS);
+            Some(reflect_local(wo, &coat.sample_visible(wo)))
+        } else {
+            refract_local(wo, &self.specular.sample_visible(wo), self.eta)
+        }
+    }
+}
+
+fn constant(value: f32) -> Arc<dyn Texture + Send> {
+    Arc::new(ConstantTexture::new(Vec3::new(value, value, value)))
+}
+
+impl Principled {
+    /// A rough dielectric of `base_color`, the other parameters start at their defaults.
+    pub fn new(base_color: Arc<dyn Texture + Send>) -> Self {
+        Principled {
+            base_color,
+            metallic: constant(0.0),
+            roughness: constant(0.5),
+            specular: constant(0.5),
+            clearcoat: constant(0.0),
+            sheen: constant(0.0),
+            transmission: constant(0.0),
+            emission: constant(0.0),
+        }
+    }
+
+    pub fn metallic(mut self, metallic: Arc<dyn Texture + Send>) -> Self {
+        self.metallic = metallic;
+        self
+    }
+
+    pub fn roughness(mut self, roughness: Arc<dyn Texture + Send>) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    pub fn specular(mut self, specular: Arc<dyn Texture + Send>) -> Self {
+        self.specular = specular;
+        self
+    }
+
+    pub fn clearcoat(mut self, clearcoat: Arc<dyn Texture + Send>) -> Self {
+        self.clearcoat = clearcoat;
+        self
+    }
+
+    pub fn sheen(mut self, sheen: Arc<dyn Texture + Send>) -> Self {
+        self.sheen = sheen;
+        self
+    }
+
+    pub fn transmission(mut self, transmission: Arc<dyn Texture + Send>) -> Self {
+        self.transmission = transmission;
+        self
+    }
+
+    pub fn emission(mut self, emission: Arc<dyn Texture + Send>) -> Self {
+        self.emission = emission;
+        self
+    }
+
+    // The surface parameters and the shading frame on the side of `wo`.
+    fn surface(&self, rec: &HitRecord, wo: &Vec3) -> (Surface, Onb) {
+        let scalar = |texture: &Arc<dyn Texture + Send>| {
+            texture.texture(rec.u, rec.v, &rec.p).x().clamp(0.0, 1.0)
+        };
+        let reflectance = (0.08 * scalar(&self.specular)).sqrt();
+        let ior = (1.0 + reflectance) / (1.0 - reflectance);
+        let entering = rec.normal.dot(*wo) > 0.0;
+        let surface = Surface {
+            base_color: self.base_color.texture(rec.u, rec.v, &rec.p),
+            metallic: scalar(&self.metallic),
+            specular: Ggx::from_roughness(scalar(&self.roughness).max(MIN_ROUGHNESS)),
+            clearcoat: scalar(&self.clearcoat),
+            sheen: scalar(&self.sheen),
+            transmission: scalar(&self.transmission),
+            ior,
+            eta: if entering { ior } else { 1.0 / ior },
+        };
+        let frame = Onb::new(if entering { rec.normal } else { -rec.normal });
+        (surface, frame)
+    }
+}
+
+impl Material for Principled {
+    fn scatter<'a>(&self, r_in: &Ray, rec: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>> {
+        let wo_world = -r_in.direction.unit_vector();
+        let (surface, frame) = self.surface(rec, &wo_world);
+        let wo = frame.world_to_local(&wo_world);
+        let direction = frame.local_to_world(&surface.sample(&wo)?);
+        // Weighted for the direction as it is handed out, so that `eval` and `pdf` agree with
+        // it exactly even where the refraction half vector is sensitive to rounding.
+        let wi = frame.world_to_local(&direction.unit_vector());
+        let pdf = surface.pdf(&wo, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord::new(
+            rec,
+            surface.eval(&wo, &wi) / pdf,
+            direction,
+            pdf,
+        ))
+    }
+
+    fn emitted(&self, _r_in: &Ray, record: &HitRecord) -> Vec3 {
+        self.emission.texture(record.u, record.v, &record.p)
+    }
+
+    fn eval(&self, rec: &HitRecord, wi: &Vec3, wo: &Vec3) -> Vec3 {
+        let wo_world = wo.unit_vector();
+        let (surface, frame) = self.surface(rec, &wo_world);
+        surface.eval(
+            &frame.world_to_local(&wo_world),
+            &frame.world_to_local(&wi.unit_vector()),
+        )
+    }
+
+    fn pdf(&self, rec: &HitRecord, wi: &Vec3, wo: &Vec3) -> f32 {
+        let wo_world = wo.unit_vector();
+        let (surface, frame) = self.surface(rec, &wo_world);
+        surface.pdf(
+            &frame.world_to_local(&wo_world),
+            &frame.world_to_local(&wi.unit_vector()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::assert_consistent_sampling;
+
+    #[test]
+    fn samples_agree_with_eval_and_pdf() {
+        let base = || constant(0.8);
+        assert_consistent_sampling(&Principled::new(base()));
+        assert_consistent_sampling(
+            &Principled::new(base())
+                .metallic(constant(1.0))
+                .roughness(constant(0.2)),
+        );
+        assert_consistent_sampling(
+            &Principled::new(base())
+                .clearcoat(constant(1.0))
+                .sheen(constant(1.0)),
+        );
+        assert_consistent_sampling(
+            &Principled::new(base())
+                .transmission(constant(1.0))
+                .roughness(constant(0.3)),
+        );
+        assert_consistent_sampling(
+            &Principled::new(base())
+                .metallic(constant(0.3))
+                .transmission(constant(0.5))
+                .clearcoat(constant(0.5)),
+        );
+    }
+}
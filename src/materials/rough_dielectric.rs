@@ -0,0 +1,183 @@
+use crate::hittables::HitRecord;
+use crate::materials::microfacet::{
+    fresnel_dielectric, generalized_half_vector, reflect_local, refract_local, refraction_jacobian,
+    Ggx,
+};
+use crate::math::{Onb, Vec3};
+use crate::physics::{Material, Ray, ScatterRecord};
+use crate::rand::prelude::*;
+use crate::textures::Texture;
+
+/// Frosted glass: a GGX microfacet BRDF and BTDF pair (Walter et al. 2007) split by the exact
+/// Fresnel reflectance of the micro normal.
+///
+/// `roughness` is perceptual and read from the red channel of the texture, 0 gives smooth
+/// glass. `tint` colours the transmitted light.
+pub struct RoughDielectric<T: Texture> {
+    roughness: T,
+    ref_idx: f32,
+    tint: Vec3,
+}
+
+// Shading frame on the side of `wo` and the relative index of the far side.
+struct Interface {
+    frame: Onb,
+    eta: f32,
+}
+
+impl<T: Texture> RoughDielectric<T> {
+    pub fn new(roughness: T, ref_idx: f32, tint: Vec3) -> Self {
+        RoughDielectric {
+            roughness,
+            ref_idx,
+            tint,
+        }
+    }
+
+    fn ggx(&self, rec: &HitRecord) -> Ggx {
+        Ggx::from_roughness(self.roughness.texture(rec.u, rec.v, &rec.p).x())
+    }
+
+    fn interface(&self, rec: &HitRecord, wo: &Vec3) -> Interface {
+        if rec.normal.dot(*wo) > 0.0 {
+            Interface {
+                frame: Onb::new(rec.normal),
+                eta: self.ref_idx,
+            }
+        } else {
+            Interface {
+                frame: Onb::new(-rec.normal),
+                eta: 1.0 / self.ref_idx,
+            }
+        }
+    }
+
+    fn smooth_scatter<'a>(
+        &self,
+        rec: &'a HitRecord<'a>,
+        frame: &Onb,
+        wo: &Vec3,
+        eta: f32,
+    ) -> Option<ScatterRecord<'a>> {
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let fresnel = fresnel_dielectric(wo.z(), eta);
+        let (attenuation, wi) = match refract_local(wo, &normal, eta) {
+            Some(refracted) if rand::thread_rng().gen::<f32>() >= fresnel => (self.tint, refracted),
+            _ => (Vec3::new(1.0, 1.0, 1.0), reflect_local(wo, &normal)),
+        };
+        Some(ScatterRecord::specular(
+            rec,
+            attenuation,
+            frame.local_to_world(&wi),
+        ))
+    }
+}
+
+impl<T: Texture> Material for RoughDielectric<T> {
+    fn scatter<'a>(&self, r_in: &Ray, rec: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>> {
+        let wo_world = -r_in.direction.unit_vector();
+        let Interface { frame, eta } = self.interface(rec, &wo_world);
+        let wo = frame.world_to_local(&wo_world);
+        let ggx = self.ggx(rec);
+        if ggx.is_smooth() {
+            return self.smooth_scatter(rec, &frame, &wo, eta);
+        }
+        let m = ggx.sample_visible(&wo);
+        let cos_m = wo.dot(m);
+        let fresnel = fresnel_dielectric(cos_m, eta);
+        let visible_pdf = ggx.visible_pdf(&wo, &m);
+        let refracted = refract_local(&wo, &m, eta);
+        match refracted {
+            Some(wi) if rand::thread_rng().gen::<f32>() >= fresnel => {
+                if wi.z() >= 0.0 {
+                    return None;
+                }
+                let jacobian = refraction_jacobian(&wo, &wi, &m, eta);
+                Some(ScatterRecord::new(
+                    rec,
+                    self.tint * (ggx.g(&wo, &wi) / ggx.g1(&wo)),
+                    frame.local_to_world(&wi),
+                    (1.0 - fresnel) * visible_pdf * jacobian,
+                ))
+            }
+            _ => {
+                let wi = reflect_local(&wo, &m);
+                if wi.z() <= 0.0 {
+                    return None;
+                }
+                let g = ggx.g(&wo, &wi) / ggx.g1(&wo);
+                Some(ScatterRecord::new(
+                    rec,
+                    Vec3::new(g, g, g),
+                    frame.local_to_world(&wi),
+                    fresnel * visible_pdf / (4.0 * cos_m),
+                ))
+            }
+        }
+    }
+
+    fn eval(&self, rec: &HitRecord, wi: &Vec3, wo: &Vec3) -> Vec3 {
+        let wo_world = wo.unit_vector();
+        let Interface { frame, eta } = self.interface(rec, &wo_world);
+        let wo = frame.world_to_local(&wo_world);
+        let wi = frame.world_to_local(&wi.unit_vector());
+        let ggx = self.ggx(rec);
+        if ggx.is_smooth() {
+            return Vec3::default();
+        }
+        let (m, transmitted) = match generalized_half_vector(&wo, &wi, eta) {
+            Some(half) => half,
+            None => return Vec3::default(),
+        };
+        let fresnel = fresnel_dielectric(wo.dot(m), eta);
+        let dg = ggx.d(&m) * ggx.g(&wo, &wi);
+        if transmitted {
+            let jacobian = refraction_jacobian(&wo, &wi, &m, eta);
+            self.tint * ((1.0 - fresnel) * dg * wo.dot(m) * jacobian / wo.z())
+        } else {
+            let f = fresnel * dg / (4.0 * wo.z());
+            Vec3::new(f, f, f)
+        }
+    }
+
+    fn pdf(&self, rec: &HitRecord, wi: &Vec3, wo: &Vec3) -> f32 {
+        let wo_world = wo.unit_vector();
+        let Interface { frame, eta } = self.interface(rec, &wo_world);
+        let wo = frame.world_to_local(&wo_world);
+        let wi = frame.world_to_local(&wi.unit_vector());
+        let ggx = self.ggx(rec);
+        if ggx.is_smooth() {
+            return 0.0;
+        }
+        let (m, transmitted) = match generalized_half_vector(&wo, &wi, eta) {
+            Some(half) => half,
+            None => return 0.0,
+        };
+        let fresnel = fresnel_dielectric(wo.dot(m), eta);
+        let visible_pdf = ggx.visible_pdf(&wo, &m);
+        if transmitted {
+            let jacobian = refraction_jacobian(&wo, &wi, &m, eta);
+            (1.0 - fresnel) * visible_pdf * jacobian
+        } else {
+            fresnel * visible_pdf / (4.0 * wo.dot(m))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::assert_consistent_sampling;
+    use crate::textures::ConstantTexture;
+
+    #[test]
+    fn samples_agree_with_eval_and_pdf() {
+        for roughness in [0.1, 0.5, 1.0] {
+            assert_consistent_sampling(&RoughDielectric::new(
+                ConstantTexture::new(Vec3::new(roughness, roughness, roughness)),
+                1.5,
+                Vec3::new(1.0, 1.0, 1.0),
+            ));
+        }
+    }
+}
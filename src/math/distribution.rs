@@ -0,0 +1,193 @@
+/// Piecewise constant density over [0, 1) proportional to `func`.
+pub struct Distribution1D {
+    func: Vec<f32>,
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<f32>) -> Self {
+        let n = func.len();
+        let mut cdf = Vec::with_capacity(n + 1);
+        cdf.push(0.0);
+        for i in 0..n {
+            cdf.push(cdf[i] + func[i] / n as f32);
+        }
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            // A function that is zero everywhere falls back to a uniform density.
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f32 / n as f32
+            };
+        }
+        Distribution1D {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+
+    /// Maps a uniform `u` to a sample, returning it with its density and the segment it is in.
+    pub fn sample(&self, u: f32) -> (f32, f32, usize) {
+        let n = self.func.len();
+        let offset = (self.cdf.partition_point(|&c| c <= u).max(1) - 1).min(n - 1);
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > 0.0 {
+            (u - self.cdf[offset]) / width
+        } else {
+            0.0
+        };
+        let x = ((offset as f32 + du) / n as f32).min(1.0 - f32::EPSILON);
+        (x, self.density(offset), offset)
+    }
+
+    pub fn pdf(&self, x: f32) -> f32 {
+        self.density(self.segment(x))
+    }
+
+    fn segment(&self, x: f32) -> usize {
+        ((x * self.func.len() as f32) as usize).min(self.func.len() - 1)
+    }
+
+    fn density(&self, segment: usize) -> f32 {
+        if self.integral > 0.0 {
+            self.func[segment] / self.integral
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Piecewise constant density over [0, 1)² proportional to a grid of `nu` by `nv` values,
+/// stored row by row. Samples pick a row first and then a column within it.
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f32], nu: usize, nv: usize) -> Self {
+        let rows: Vec<Distribution1D> = func
+            .chunks(nu)
+            .take(nv)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral()).collect());
+        Distribution2D { rows, marginal }
+    }
+
+    /// Maps two uniform numbers to a point `(u, v)` and its density.
+    pub fn sample(&self, u0: f32, u1: f32) -> (f32, f32, f32) {
+        let (v, pdf_v, row) = self.marginal.sample(u1);
+        let (u, pdf_u, _) = self.rows[row].sample(u0);
+        (u, v, pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, u: f32, v: f32) -> f32 {
+        let row = &self.rows[self.marginal.segment(v)];
+        self.marginal.pdf(v) * row.pdf(u)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    const SAMPLES: usize = 200_000;
+
+    #[test]
+    fn samples_1d_in_proportion_to_the_pdf() {
+        let func = vec![1.0, 0.0, 3.0, 2.0, 0.5, 4.0];
+        let n = func.len();
+        let distribution = Distribution1D::new(func);
+        let total: f32 = (0..n)
+            .map(|i| distribution.pdf((i as f32 + 0.5) / n as f32) / n as f32)
+            .sum();
+        assert!((total - 1.0).abs() < 1e-5, "pdf integrates to {}", total);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut counts = vec![0; n];
+        for _ in 0..SAMPLES {
+            let (x, pdf, offset) = distribution.sample(rng.gen());
+            assert!((0.0..1.0).contains(&x), "sample {}", x);
+            assert_eq!(offset, distribution.segment(x));
+            assert_eq!(pdf, distribution.pdf(x));
+            counts[offset] += 1;
+        }
+        for (i, &count) in counts.iter().enumerate() {
+            let expected = distribution.pdf((i as f32 + 0.5) / n as f32) / n as f32;
+            let actual = count as f32 / SAMPLES as f32;
+            assert!(
+                (actual - expected).abs() < 0.01,
+                "segment {}: {} of samples, expected {}",
+                i,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn zero_function_samples_uniformly_with_zero_pdf() {
+        let distribution = Distribution1D::new(vec![0.0; 4]);
+        assert_eq!(distribution.integral(), 0.0);
+        for u in [0.0, 0.3, 0.6, 0.99] {
+            let (x, pdf, offset) = distribution.sample(u);
+            assert!((x - u).abs() < 1e-6, "{} maps to {}", u, x);
+            assert_eq!(offset, distribution.segment(x));
+            assert_eq!(pdf, 0.0);
+        }
+    }
+
+    #[test]
+    fn samples_2d_in_proportion_to_the_pdf() {
+        let (nu, nv) = (4, 3);
+        let func = [
+            1.0, 2.0, 0.0, 1.0, //
+            0.0, 0.0, 0.0, 0.0, //
+            5.0, 0.5, 3.0, 2.0,
+        ];
+        let distribution = Distribution2D::new(&func, nu, nv);
+        let cell = |i: usize, j: usize| {
+            let u = (i as f32 + 0.5) / nu as f32;
+            let v = (j as f32 + 0.5) / nv as f32;
+            distribution.pdf(u, v) / (nu * nv) as f32
+        };
+        let total: f32 = (0..nv)
+            .flat_map(|j| (0..nu).map(move |i| (i, j)))
+            .map(|(i, j)| cell(i, j))
+            .sum();
+        assert!((total - 1.0).abs() < 1e-5, "pdf integrates to {}", total);
+
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut counts = vec![0; nu * nv];
+        for _ in 0..SAMPLES {
+            let (u, v, pdf) = distribution.sample(rng.gen(), rng.gen());
+            assert!((0.0..1.0).contains(&u) && (0.0..1.0).contains(&v));
+            assert!((pdf - distribution.pdf(u, v)).abs() <= 1e-5 * pdf);
+            let i = ((u * nu as f32) as usize).min(nu - 1);
+            let j = ((v * nv as f32) as usize).min(nv - 1);
+            counts[i + nu * j] += 1;
+        }
+        for j in 0..nv {
+            for i in 0..nu {
+                let actual = counts[i + nu * j] as f32 / SAMPLES as f32;
+                assert!(
+                    (actual - cell(i, j)).abs() < 0.01,
+                    "cell ({}, {}): {} of samples, expected {}",
+                    i,
+                    j,
+                    actual,
+                    cell(i, j)
+                );
+            }
+        }
+    }
+}
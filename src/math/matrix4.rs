@@ -0,0 +1,263 @@
+use crate::math::Vec3;
+use std::f32::consts::PI;
+
+// Row-major 4x4 matrix acting on column vectors.
+#[derive(Debug, Copy, Clone)]
+pub struct Matrix4 {
+    m: [[f32; 4]; 4],
+}
+
+impl Matrix4 {
+    pub fn new(m: [[f32; 4]; 4]) -> Self {
+        Matrix4 { m }
+    }
+
+    pub fn identity() -> Self {
+        Matrix4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        Matrix4::new([
+            [1.0, 0.0, 0.0, offset.x()],
+            [0.0, 1.0, 0.0, offset.y()],
+            [0.0, 0.0, 1.0, offset.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scaling(scale: Vec3) -> Self {
+        Matrix4::new([
+            [scale.x(), 0.0, 0.0, 0.0],
+            [0.0, scale.y(), 0.0, 0.0],
+            [0.0, 0.0, scale.z(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // Angles are in degrees, like `RotateY`.
+    pub fn rotation_x(angle: f32) -> Self {
+        let (sin, cos) = (angle * PI / 180.0).sin_cos();
+        Matrix4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, cos, -sin, 0.0],
+            [0.0, sin, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotation_y(angle: f32) -> Self {
+        let (sin, cos) = (angle * PI / 180.0).sin_cos();
+        Matrix4::new([
+            [cos, 0.0, sin, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [-sin, 0.0, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotation_z(angle: f32) -> Self {
+        let (sin, cos) = (angle * PI / 180.0).sin_cos();
+        Matrix4::new([
+            [cos, -sin, 0.0, 0.0],
+            [sin, cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // Rodrigues' rotation around an arbitrary axis.
+    pub fn rotation(axis: Vec3, angle: f32) -> Self {
+        let a = axis.unit_vector();
+        let (sin, cos) = (angle * PI / 180.0).sin_cos();
+        let t = 1.0 - cos;
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        Matrix4::new([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // Places an object at `from` with its +z axis pointing at `to`. `None` when `from` and
+    // `to` coincide or `up` is zero or parallel to the view direction.
+    pub fn look_at(from: Vec3, to: Vec3, up: Vec3) -> Option<Self> {
+        let view = to - from;
+        if view.squared_length() == 0.0 || up.squared_length() == 0.0 {
+            return None;
+        }
+        let w = view.unit_vector();
+        let side = up.unit_vector().cross(w);
+        if side.length() <= 1e-6 {
+            return None;
+        }
+        let u = side.unit_vector();
+        let v = w.cross(u);
+        Some(Matrix4::new([
+            [u.x(), v.x(), w.x(), from.x()],
+            [u.y(), v.y(), w.y(), from.y()],
+            [u.z(), v.z(), w.z(), from.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ]))
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Matrix4::new(m)
+    }
+
+    // Gauss-Jordan elimination with partial pivoting, `None` for singular matrices and ones
+    // with non-finite entries.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Matrix4::identity().m;
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if !a[pivot][col].is_finite() || a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+            let k = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= k;
+                inv[col][j] *= k;
+            }
+            for row in 0..4 {
+                if row != col {
+                    let f = a[row][col];
+                    for j in 0..4 {
+                        a[row][j] -= f * a[col][j];
+                        inv[row][j] -= f * inv[col][j];
+                    }
+                }
+            }
+        }
+        if inv.iter().flatten().all(|value| value.is_finite()) {
+            Some(Matrix4::new(inv))
+        } else {
+            None
+        }
+    }
+
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3],
+            m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3],
+            m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3],
+        )
+    }
+
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+}
+
+impl std::ops::Mul for Matrix4 {
+    type Output = Self;
+    fn mul(self, rhs: Matrix4) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Matrix4::new(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_identity(m: &Matrix4) {
+        let identity = Matrix4::identity();
+        for (row, expected) in m.m.iter().zip(identity.m.iter()) {
+            for (value, expected) in row.iter().zip(expected.iter()) {
+                assert!((value - expected).abs() < 1e-5, "{:?}", m);
+            }
+        }
+    }
+
+    #[test]
+    fn inverse_undoes_the_matrix() {
+        let m = Matrix4::translation(Vec3::new(1.0, -2.0, 3.0))
+            * Matrix4::rotation(Vec3::new(1.0, 1.0, 0.0), 37.0)
+            * Matrix4::scaling(Vec3::new(2.0, 0.5, -3.0));
+        let inverse = m.inverse().unwrap();
+        assert_identity(&(m * inverse));
+        assert_identity(&(inverse * m));
+
+        let p = Vec3::new(0.3, 4.0, -1.5);
+        let back = inverse.transform_point(&m.transform_point(&p));
+        assert!((back - p).length() < 1e-5);
+    }
+
+    #[test]
+    fn inverse_pivots_around_zero_diagonals() {
+        let m = Matrix4::rotation_z(90.0) * Matrix4::translation(Vec3::new(0.0, 0.0, 5.0));
+        assert_identity(&(m * m.inverse().unwrap()));
+    }
+
+    #[test]
+    fn singular_and_non_finite_matrices_have_no_inverse() {
+        assert!(Matrix4::scaling(Vec3::new(1.0, 0.0, 1.0))
+            .inverse()
+            .is_none());
+        let mut m = Matrix4::identity().m;
+        m[2] = m[1];
+        assert!(Matrix4::new(m).inverse().is_none());
+        assert!(Matrix4::translation(Vec3::new(f32::NAN, 0.0, 0.0))
+            .inverse()
+            .is_none());
+        assert!(Matrix4::scaling(Vec3::new(1.0, f32::INFINITY, 1.0))
+            .inverse()
+            .is_none());
+    }
+
+    #[test]
+    fn look_at_rejects_degenerate_frames() {
+        let from = Vec3::new(1.0, 2.0, 3.0);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let m = Matrix4::look_at(from, Vec3::new(1.0, 2.0, 10.0), up).unwrap();
+        assert!(
+            (m.transform_point(&Vec3::new(0.0, 0.0, 7.0)) - Vec3::new(1.0, 2.0, 10.0)).length()
+                < 1e-5
+        );
+
+        assert!(Matrix4::look_at(from, from, up).is_none());
+        assert!(Matrix4::look_at(from, Vec3::new(1.0, 5.0, 3.0), up).is_none());
+        assert!(Matrix4::look_at(from, Vec3::new(1.0, 2.0, 10.0), Vec3::default()).is_none());
+    }
+}
@@ -1,4 +1,8 @@
+mod distribution;
+mod matrix4;
+mod onb;
 mod perlin;
+pub mod sampling;
 mod vec3;
 
-pub use self::{perlin::*, vec3::*};
+pub use self::{distribution::*, matrix4::*, onb::*, perlin::*, vec3::*};
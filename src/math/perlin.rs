@@ -2,6 +2,7 @@ use crate::math::{dot, Vec3};
 use crate::rand::prelude::*;
 
 #[inline]
+#[allow(clippy::needless_range_loop)]
 fn perlin_interp(c: [[[Vec3; 2]; 2]; 2], u: f32, v: f32, w: f32) -> f32 {
     let uu = u * u * (3.0 - 2.0 * u);
     let vv = v * v * (3.0 - 2.0 * v);
@@ -80,7 +81,7 @@ impl Perlin {
 fn perlin_generate() -> [Vec3; 256] {
     let mut rng = thread_rng();
     let mut p = [Vec3::default(); 256];
-    for (_, el) in p.iter_mut().enumerate() {
+    for el in p.iter_mut() {
         let x_random = 2.0 * rng.gen::<f32>() - 1.0;
         let y_random = 2.0 * rng.gen::<f32>() - 1.0;
         let z_random = 2.0 * rng.gen::<f32>() - 1.0;
@@ -0,0 +1,77 @@
+//! Random points and directions for Monte Carlo integration.
+//!
+//! Hemisphere and cone samplers work around +z, use an `Onb` to orient them. Every sampler
+//! that is importance sampled has a matching `_pdf` function giving the density per unit
+//! solid angle.
+
+use crate::math::Vec3;
+use crate::rand::prelude::*;
+use std::f32::consts::PI;
+
+pub fn random_in_unit_sphere() -> Vec3 {
+    let mut rng = rand::thread_rng();
+    let mut p = 2.0 * Vec3::new(rng.gen(), rng.gen(), rng.gen()) - Vec3::new(1.0, 1.0, 1.0);
+    while p.squared_length() >= 1.0 {
+        p = 2.0 * Vec3::new(rng.gen(), rng.gen(), rng.gen()) - Vec3::new(1.0, 1.0, 1.0);
+    }
+    p
+}
+
+pub fn random_in_unit_disk() -> Vec3 {
+    let mut rng = rand::thread_rng();
+    let mut p = 2.0 * Vec3::new(rng.gen(), rng.gen(), 0.0) - Vec3::new(1.0, 1.0, 0.0);
+    while p.squared_length() >= 1.0 {
+        p = 2.0 * Vec3::new(rng.gen(), rng.gen(), 0.0) - Vec3::new(1.0, 1.0, 0.0);
+    }
+    p
+}
+
+pub fn uniform_sphere() -> Vec3 {
+    let mut rng = rand::thread_rng();
+    let z = 1.0 - 2.0 * rng.gen::<f32>();
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * rng.gen::<f32>();
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_sphere_pdf() -> f32 {
+    1.0 / (4.0 * PI)
+}
+
+pub fn uniform_hemisphere() -> Vec3 {
+    let mut rng = rand::thread_rng();
+    let z = rng.gen::<f32>();
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * rng.gen::<f32>();
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_hemisphere_pdf() -> f32 {
+    1.0 / (2.0 * PI)
+}
+
+// Malley's method: points uniform on the disk, projected up to the hemisphere.
+pub fn cosine_hemisphere() -> Vec3 {
+    let mut rng = rand::thread_rng();
+    let r = rng.gen::<f32>().sqrt();
+    let phi = 2.0 * PI * rng.gen::<f32>();
+    let (x, y) = (r * phi.cos(), r * phi.sin());
+    Vec3::new(x, y, (1.0 - x * x - y * y).max(0.0).sqrt())
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: f32) -> f32 {
+    cos_theta.max(0.0) / PI
+}
+
+/// Uniform over the directions within the cone whose half angle has cosine `cos_theta_max`.
+pub fn uniform_cone(cos_theta_max: f32) -> Vec3 {
+    let mut rng = rand::thread_rng();
+    let z = 1.0 - rng.gen::<f32>() * (1.0 - cos_theta_max);
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * rng.gen::<f32>();
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_cone_pdf(cos_theta_max: f32) -> f32 {
+    1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+}
@@ -59,6 +59,14 @@ impl Vec3 {
         )
     }
 
+    pub fn max_component(&self) -> f32 {
+        self.x().max(self.y()).max(self.z())
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.0.is_finite() && self.1.is_finite() && self.2.is_finite()
+    }
+
     pub fn unit_vector(self) -> Vec3 {
         self / self.length()
     }
@@ -21,6 +21,7 @@ fn ffmax(a: f32, b: f32) -> f32 {
 
 // Axis Aligned Bounding Box
 #[derive(Copy, Clone)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct AABB {
     pub min: Vec3,
     pub max: Vec3,
@@ -31,6 +32,11 @@ impl AABB {
         AABB { min, max }
     }
 
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
     pub fn hit(&self, ray: &Ray, mut t_min: f32, mut t_max: f32) -> Option<(f32, f32)> {
         for a in 0..3 {
             let inv_d = 1.0 / ray.direction[a];
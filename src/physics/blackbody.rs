@@ -0,0 +1,45 @@
+use crate::math::Vec3;
+
+// Analytic fit of the CIE 1931 colour matching functions, Wyman et al. 2013.
+fn lobe(wavelength: f64, mean: f64, below: f64, above: f64) -> f64 {
+    let sigma = if wavelength < mean { below } else { above };
+    let t = (wavelength - mean) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+fn cie_xyz(wavelength: f64) -> (f64, f64, f64) {
+    let x = 1.056 * lobe(wavelength, 599.8, 37.9, 31.0)
+        + 0.362 * lobe(wavelength, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(wavelength, 501.1, 20.4, 26.2);
+    let y =
+        0.821 * lobe(wavelength, 568.8, 46.9, 40.5) + 0.286 * lobe(wavelength, 530.9, 16.3, 31.1);
+    let z =
+        1.217 * lobe(wavelength, 437.0, 11.8, 36.0) + 0.681 * lobe(wavelength, 459.0, 26.0, 13.8);
+    (x, y, z)
+}
+
+// Spectral radiance of a black body by Planck's law, up to a constant factor.
+fn planck(wavelength_nm: f64, kelvin: f64) -> f64 {
+    const C2: f64 = 1.438_777e-2; // h c / k in m K
+    let wavelength = wavelength_nm * 1e-9;
+    1.0 / (wavelength.powi(5) * ((C2 / (wavelength * kelvin)).exp() - 1.0))
+}
+
+/// Linear sRGB colour of a black body at `kelvin`, scaled to a luminance of one.
+pub fn blackbody(kelvin: f32) -> Vec3 {
+    let kelvin = f64::from(kelvin.max(1.0));
+    let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+    for step in 0..=80 {
+        let wavelength = 380.0 + 5.0 * f64::from(step);
+        let radiance = planck(wavelength, kelvin);
+        let (cx, cy, cz) = cie_xyz(wavelength);
+        x += radiance * cx;
+        y += radiance * cy;
+        z += radiance * cz;
+    }
+    let (x, z) = (x / y, z / y);
+    let r = 3.2406 * x - 1.5372 - 0.4986 * z;
+    let g = -0.9689 * x + 1.8758 + 0.0415 * z;
+    let b = 0.0557 * x - 0.2040 + 1.0570 * z;
+    Vec3::new(r.max(0.0) as f32, g.max(0.0) as f32, b.max(0.0) as f32)
+}
@@ -1,16 +1,8 @@
-use crate::math::{dot, Vec3};
+use crate::math::sampling::random_in_unit_disk;
+use crate::math::Vec3;
 use crate::physics::Ray;
 use crate::rand::prelude::*;
 
-fn random_in_unit_disk() -> Vec3 {
-    let mut rng = rand::thread_rng();
-    let mut p = 2.0 * Vec3::new(rng.gen(), rng.gen(), 0.0) - Vec3::new(1.0, 1.0, 0.0);
-    while dot(&p, &p) >= 1.0 {
-        p = 2.0 * Vec3::new(rng.gen(), rng.gen(), 0.0) - Vec3::new(1.0, 1.0, 0.0);
-    }
-    p
-}
-
 pub struct Camera {
     lower_left_corner: Vec3,
     horizontal: Vec3,
@@ -24,6 +16,7 @@ pub struct Camera {
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lookfrom: Vec3,
         lookat: Vec3,
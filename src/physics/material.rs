@@ -2,16 +2,99 @@ use crate::hittables::HitRecord;
 use crate::math::Vec3;
 use crate::physics::Ray;
 
+/// Result of `Material::scatter`: a sampled incoming direction and its weight.
+///
+/// Directions follow the usual convention: `wo` points back along the ray that hit the
+/// surface and `wi` along the scattered ray, both away from the hit point.
+pub struct ScatterRecord<'a> {
+    /// `eval(direction, wo) / pdf` for sampled lobes, the reflectance for specular ones.
+    pub attenuation: Vec3,
+    pub direction: Vec3,
+    /// Density of `direction` per unit solid angle, meaningless when `specular`.
+    pub pdf: f32,
+    /// Set for delta lobes like mirrors and glass, which `eval` can never hit.
+    pub specular: bool,
+    record: &'a HitRecord<'a>,
+}
+
+impl<'a> ScatterRecord<'a> {
+    pub fn new(record: &'a HitRecord<'a>, attenuation: Vec3, direction: Vec3, pdf: f32) -> Self {
+        ScatterRecord {
+            attenuation,
+            direction,
+            pdf,
+            specular: false,
+            record,
+        }
+    }
+
+    pub fn specular(record: &'a HitRecord<'a>, attenuation: Vec3, direction: Vec3) -> Self {
+        ScatterRecord {
+            attenuation,
+            direction,
+            pdf: 0.0,
+            specular: true,
+            record,
+        }
+    }
+
+    /// The same sample for another record of the hit, used by materials that scatter with a
+    /// modified copy of the record they were given.
+    pub fn with_record<'b>(self, record: &'b HitRecord<'b>) -> ScatterRecord<'b> {
+        ScatterRecord {
+            attenuation: self.attenuation,
+            direction: self.direction,
+            pdf: self.pdf,
+            specular: self.specular,
+            record,
+        }
+    }
+
+    /// The scattered ray, leaving the hit point at `time`.
+    pub fn ray(&self, time: f32) -> Ray {
+        Ray::new(self.record.p, self.direction, time)
+    }
+
+    /// BSDF times the cosine of `wi` at the hit point the scattering happened at.
+    pub fn eval(&self, wi: &Vec3, wo: &Vec3) -> Vec3 {
+        self.record.material.eval(self.record, wi, wo)
+    }
+}
+
 pub trait Material: Sync {
-    fn scatter(
-        &self,
-        r: &Ray,
-        record: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool;
-
-    fn emitted(&self, _u: f32, _v: f32, _p: &Vec3) -> Vec3 {
+    /// Samples a direction to continue `r_in` in, `None` when the ray is absorbed.
+    fn scatter<'a>(&self, r_in: &Ray, record: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>>;
+
+    /// Light leaving the surface at `record` back along `r_in`.
+    fn emitted(&self, _r_in: &Ray, _record: &HitRecord) -> Vec3 {
         Vec3::new(0.0, 0.0, 0.0)
     }
+
+    /// BSDF times the cosine of `wi`, zero for specular materials.
+    fn eval(&self, _record: &HitRecord, _wi: &Vec3, _wo: &Vec3) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    /// Density with which `scatter` picks `wi` when seen from `wo`.
+    fn pdf(&self, _record: &HitRecord, _wi: &Vec3, _wo: &Vec3) -> f32 {
+        0.0
+    }
+}
+
+impl<T: Material + Send + ?Sized> Material for std::sync::Arc<T> {
+    fn scatter<'a>(&self, r_in: &Ray, record: &'a HitRecord<'a>) -> Option<ScatterRecord<'a>> {
+        (**self).scatter(r_in, record)
+    }
+
+    fn emitted(&self, r_in: &Ray, record: &HitRecord) -> Vec3 {
+        (**self).emitted(r_in, record)
+    }
+
+    fn eval(&self, record: &HitRecord, wi: &Vec3, wo: &Vec3) -> Vec3 {
+        (**self).eval(record, wi, wo)
+    }
+
+    fn pdf(&self, record: &HitRecord, wi: &Vec3, wo: &Vec3) -> f32 {
+        (**self).pdf(record, wi, wo)
+    }
 }
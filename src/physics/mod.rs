@@ -1,6 +1,7 @@
 mod aabb;
+mod blackbody;
 mod camera;
 mod material;
 mod ray;
 
-pub use self::{aabb::*, camera::*, material::*, ray::*};
+pub use self::{aabb::*, blackbody::*, camera::*, material::*, ray::*};
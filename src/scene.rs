@@ -1,43 +1,138 @@
 use crate::hittables::{Hitable, HittableList};
+use crate::integrators::{Integrator, PathStats};
+use crate::lights::{DeltaLight, EnvironmentLight};
 use crate::math::Vec3;
 use crate::physics::{Camera, Ray};
 use rand::prelude::*;
 use rayon::prelude::*;
+use std::time::Instant;
+
+/// Image size, sample count and path length limits of a render.
+#[derive(Copy, Clone, Debug)]
+pub struct RenderSettings {
+    pub width: u32,
+    pub height: u32,
+    pub rays_per_pixel: u32,
+    /// Paths end after this many bounces.
+    pub max_depth: u32,
+    /// Bounces before Russian roulette starts terminating paths by their throughput.
+    pub roulette_depth: u32,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            width: 1920,
+            height: 1080,
+            rays_per_pixel: 100,
+            max_depth: 50,
+            roulette_depth: 5,
+        }
+    }
+}
 
 pub struct Scene {
     camera: Camera,
     objects: HittableList,
-    width: u32,
-    height: u32,
-    rays_per_pixel: u32,
+    lights: HittableList,
+    delta_lights: Vec<Box<dyn DeltaLight>>,
+    environment: Option<EnvironmentLight>,
+    integrator: Box<dyn Integrator>,
+    settings: RenderSettings,
 }
 
 impl Scene {
     pub fn new(
         camera: Camera,
         objects: HittableList,
-        width: u32,
-        height: u32,
-        rays_per_pixel: u32,
+        lights: HittableList,
+        delta_lights: Vec<Box<dyn DeltaLight>>,
+        environment: Option<EnvironmentLight>,
+        integrator: Box<dyn Integrator>,
+        settings: RenderSettings,
     ) -> Self {
         Scene {
             camera,
             objects,
-            width,
-            height,
-            rays_per_pixel,
+            lights,
+            delta_lights,
+            environment,
+            integrator,
+            settings,
         }
     }
 
-    pub fn render(&self) -> Vec<(u8, u8, u8)> {
-        let nx = self.width;
-        let ny = self.height;
-        let ns = self.rays_per_pixel;
-        (0..ny)
+    pub fn objects(&self) -> &HittableList {
+        &self.objects
+    }
+
+    // Lights that are sampled directly, they are part of `objects` as well.
+    pub fn lights(&self) -> &HittableList {
+        &self.lights
+    }
+
+    // Point, spot and directional lights, which nothing but light sampling can reach.
+    pub fn delta_lights(&self) -> &[Box<dyn DeltaLight>] {
+        &self.delta_lights
+    }
+
+    // Light arriving along rays that leave the scene, black without one.
+    pub fn environment(&self) -> Option<&EnvironmentLight> {
+        self.environment.as_ref()
+    }
+
+    pub fn has_lights(&self) -> bool {
+        !self.lights.entities.is_empty()
+            || !self.delta_lights.is_empty()
+            || self.environment.is_some()
+    }
+
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+
+    pub fn width(&self) -> u32 {
+        self.settings.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.settings.height
+    }
+
+    pub fn rays_per_pixel(&self) -> u32 {
+        self.settings.rays_per_pixel
+    }
+
+    pub fn set_rays_per_pixel(&mut self, rays_per_pixel: u32) {
+        self.settings.rays_per_pixel = rays_per_pixel;
+    }
+
+    // Primary rays through seeded random pixel positions, so every run traces the same set.
+    // Only the intersection tests are timed; returns the hit count and the elapsed seconds.
+    pub fn trace_primary_rays(&self, count: usize) -> (usize, f64) {
+        let mut rng = StdRng::seed_from_u64(0);
+        let rays: Vec<Ray> = (0..count)
+            .map(|_| self.camera.get_ray(rng.gen(), rng.gen()))
+            .collect();
+        let start = Instant::now();
+        let hits = rays
+            .par_iter()
+            .filter(|ray| self.objects.hit(ray, 0.001, f32::MAX).is_some())
+            .count();
+        (hits, start.elapsed().as_secs_f64())
+    }
+
+    // Each row counts its own paths, the counts are added up once the rows are done.
+    pub fn render(&self) -> (Vec<(u8, u8, u8)>, PathStats) {
+        let nx = self.settings.width;
+        let ny = self.settings.height;
+        let ns = self.settings.rays_per_pixel;
+        let rows = (0..ny)
             .into_par_iter()
             .rev()
-            .flat_map(|y| {
-                (0..nx)
+            .map(|y| {
+                let mut stats = PathStats::default();
+                let row = (0..nx)
                     .map(|x| {
                         let mut col = Vec3::default();
                         for _s in 0..ns {
@@ -45,7 +140,7 @@ impl Scene {
                             let u = (x as f32 + rng.gen::<f32>()) / nx as f32;
                             let v = (y as f32 + rng.gen::<f32>()) / ny as f32;
                             let ray = self.camera.get_ray(u, v);
-                            col += color(&ray, &self.objects, 0);
+                            col += self.integrator.color(&ray, self, &mut stats);
                         }
                         col /= ns as f32;
                         col = Vec3::new(col.x().sqrt(), col.y().sqrt(), col.z().sqrt());
@@ -55,28 +150,16 @@ impl Scene {
                         let b = (255.99 * col.b()).min(255.0) as u8;
                         (r, g, b)
                     })
-                    .collect::<Vec<(u8, u8, u8)>>()
+                    .collect::<Vec<(u8, u8, u8)>>();
+                (row, stats)
             })
-            .collect::<Vec<(u8, u8, u8)>>()
-    }
-}
-
-fn color(r: &Ray, world: &HittableList, depth: i32) -> Vec3 {
-    match world.hit(&r, 0.001, std::f32::MAX) {
-        Some(rec) => {
-            let mut scattered = Ray::default();
-            let mut attenuation = Vec3::default();
-            let emitted = rec.material.emitted(rec.u, rec.v, &rec.p);
-            if depth < 50
-                && rec
-                    .material
-                    .scatter(r, &rec, &mut attenuation, &mut scattered)
-            {
-                emitted + attenuation * color(&scattered, world, depth + 1)
-            } else {
-                emitted
-            }
+            .collect::<Vec<(Vec<(u8, u8, u8)>, PathStats)>>();
+        let mut pixels = Vec::with_capacity((nx * ny) as usize);
+        let mut stats = PathStats::default();
+        for (row, row_stats) in rows {
+            pixels.extend(row);
+            stats.add(&row_stats);
         }
-        None => Vec3::default(),
+        (pixels, stats)
     }
 }
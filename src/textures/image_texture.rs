@@ -1,5 +1,8 @@
 use crate::math::Vec3;
 use crate::textures::Texture;
+use image::io::Reader as ImageReader;
+use image::{ImageResult, RgbImage};
+use std::path::Path;
 
 #[derive(Clone)]
 pub struct ImageTexture {
@@ -12,20 +15,90 @@ impl ImageTexture {
     pub fn new(data: Vec<u8>, nx: usize, ny: usize) -> Self {
         ImageTexture { data, nx, ny }
     }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        let image = ImageReader::open(path)?.decode()?.into_rgb8();
+        Ok(ImageTexture::from_rgb(image))
+    }
+
+    /// Opens a mask, the alpha channel of images that have one and the colour otherwise.
+    pub fn open_alpha<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        let image = ImageReader::open(path)?.decode()?;
+        if !image.color().has_alpha() {
+            return Ok(ImageTexture::from_rgb(image.into_rgb8()));
+        }
+        let image = image.into_rgba8();
+        let data = image.pixels().flat_map(|pixel| [pixel[3]; 3]).collect();
+        Ok(ImageTexture::new(
+            data,
+            image.width() as usize,
+            image.height() as usize,
+        ))
+    }
+
+    fn from_rgb(image: RgbImage) -> Self {
+        ImageTexture::new(
+            image.as_raw().to_vec(),
+            image.width() as usize,
+            image.height() as usize,
+        )
+    }
 }
 
 impl Texture for ImageTexture {
     fn texture(&self, u: f32, v: f32, _p: &Vec3) -> Vec3 {
+        let (u, v) = (wrap(u), wrap(v));
         let i = (u * self.nx as f32).max(0.0).min(self.nx as f32 - 1.0);
         let j = (((1.0 - v) * self.ny as f32) - 0.001)
             .max(0.0)
-            .min(self.nx as f32 - 1.0);
+            .min(self.ny as f32 - 1.0);
         let i = i as usize;
         let j = j as usize;
 
-        let r = f32::from(self.data[(3 * i + 3 * self.nx * j) as usize]) / 255.0;
-        let g = f32::from(self.data[(3 * i + 3 * self.nx * j + 1) as usize]) / 255.0;
-        let b = f32::from(self.data[(3 * i + 3 * self.nx * j + 2) as usize]) / 255.0;
+        let r = f32::from(self.data[3 * i + 3 * self.nx * j]) / 255.0;
+        let g = f32::from(self.data[3 * i + 3 * self.nx * j + 1]) / 255.0;
+        let b = f32::from(self.data[3 * i + 3 * self.nx * j + 2]) / 255.0;
         Vec3::new(r, g, b)
     }
 }
+
+// Texture coordinates outside [0, 1] repeat the image, as OBJ files expect.
+fn wrap(x: f32) -> f32 {
+    if (0.0..=1.0).contains(&x) {
+        x
+    } else {
+        x.rem_euclid(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 4x2 image whose pixels are numbered in their red channel, row by row from the top.
+    fn numbered() -> ImageTexture {
+        ImageTexture::new((0..8).flat_map(|n| [n, 0, 0]).collect(), 4, 2)
+    }
+
+    fn pixel(texture: &ImageTexture, u: f32, v: f32) -> u8 {
+        (texture.texture(u, v, &Vec3::default()).r() * 255.0).round() as u8
+    }
+
+    #[test]
+    fn lookups_stay_inside_wide_images() {
+        let texture = numbered();
+        assert_eq!(pixel(&texture, 0.1, 0.9), 0);
+        assert_eq!(pixel(&texture, 0.9, 0.1), 7);
+        assert_eq!(pixel(&texture, 0.1, 0.0), 4);
+        assert_eq!(pixel(&texture, 0.9, 1.0), 3);
+    }
+
+    #[test]
+    fn coordinates_outside_the_image_tile() {
+        let texture = numbered();
+        assert_eq!(pixel(&texture, 0.1, -0.5), pixel(&texture, 0.1, 0.5));
+        assert_eq!(pixel(&texture, 1.1, 0.9), 0);
+        assert_eq!(pixel(&texture, -0.1, 1.9), 3);
+        assert_eq!(pixel(&texture, 0.6, -0.75), 6);
+    }
+}
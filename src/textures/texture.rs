@@ -3,3 +3,9 @@ use crate::math::Vec3;
 pub trait Texture: Sync {
     fn texture(&self, u: f32, v: f32, p: &Vec3) -> Vec3;
 }
+
+impl<T: Texture + Send + ?Sized> Texture for std::sync::Arc<T> {
+    fn texture(&self, u: f32, v: f32, p: &Vec3) -> Vec3 {
+        (**self).texture(u, v, p)
+    }
+}